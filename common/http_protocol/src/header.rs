@@ -11,5 +11,8 @@ pub const APPLICATION_TABLE: &str = "application/table";
 pub const APPLICATION_STAR: &str = "application/*";
 pub const STAR_STAR: &str = "*/*";
 
+/// set on a query response which only holds the batches completed before the query timed out
+pub const TRUNCATED: &str = "x-cnosdb-truncated";
+
 /// basic auth
 pub const BASIC_PREFIX: &str = "Basic ";
@@ -11,6 +11,8 @@ pub const NOT_FOUND: StatusCode = StatusCode::NOT_FOUND;
 pub const METHOD_NOT_ALLOWED: StatusCode = StatusCode::METHOD_NOT_ALLOWED;
 /// 请求的消息体过大，超过限制
 pub const PAYLOAD_TOO_LARGE: StatusCode = StatusCode::PAYLOAD_TOO_LARGE;
+/// 请求过于频繁，超过限制
+pub const TOO_MANY_REQUESTS: StatusCode = StatusCode::TOO_MANY_REQUESTS;
 /// 操作执行失败
 pub const UNPROCESSABLE_ENTITY: StatusCode = StatusCode::UNPROCESSABLE_ENTITY;
 
@@ -31,6 +31,10 @@ pub const STRING_CODEC: [Encoding; 7] = [
 
 pub const BOOLEAN_CODEC: [Encoding; 3] = [Encoding::Default, Encoding::Null, Encoding::BitPack];
 
+// Block compressors store opaque bytes, only the default encoding leaves
+// the engine free to dictionary encode the values
+pub const DICTIONARY_CODEC: [Encoding; 1] = [Encoding::Default];
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize, Hash)]
 pub enum Encoding {
     Default = 0,
@@ -72,6 +76,10 @@ impl Encoding {
         BOOLEAN_CODEC.contains(self)
     }
 
+    pub fn is_dictionary_encoding(&self) -> bool {
+        DICTIONARY_CODEC.contains(self)
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Encoding::Default => "DEFAULT",
@@ -7,7 +7,7 @@
 //!         - Column #3
 //!         - Column #4
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::{collections::BTreeMap, sync::Arc};
 
@@ -28,6 +28,10 @@ use datafusion::datasource::file_format::parquet::ParquetFormat;
 use datafusion::datasource::file_format::FileFormat;
 use datafusion::datasource::listing::ListingOptions;
 use datafusion::error::{DataFusionError, Result as DataFusionResult};
+use datafusion::logical_expr::{utils::expr_to_columns, Expr};
+use datafusion::physical_plan::ColumnStatistics;
+use snafu::Snafu;
+use utils::BkdrHasher;
 
 use crate::codec::Encoding;
 use crate::{ColumnId, SchemaId, ValueType};
@@ -38,8 +42,147 @@ pub const TIME_FIELD_NAME: &str = "time";
 
 pub const FIELD_ID: &str = "_field_id";
 pub const TAG: &str = "_tag";
+pub const PARQUET_LOGICAL_TYPE: &str = "_parquet_logical_type";
+/// Every arrow field metadata key written by `to_arrow_schema` and `parquet_export_schema`
+const RESERVED_METADATA_KEYS: [&str; 3] = [FIELD_ID, TAG, PARQUET_LOGICAL_TYPE];
 pub const TIME_FIELD: &str = "time";
 
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum SchemaError {
+    #[snafu(display(
+        "Column '{}' has conflicting types: {} and {}",
+        name,
+        left_type,
+        right_type
+    ))]
+    ColumnTypeConflict {
+        name: String,
+        left_type: ColumnType,
+        right_type: ColumnType,
+    },
+
+    #[snafu(display("Column '{}' not found", name))]
+    ColumnNotFound { name: String },
+
+    #[snafu(display("Column '{}' has type {}, expected time", name, column_type))]
+    NotTimeColumn {
+        name: String,
+        column_type: ColumnType,
+    },
+
+    #[snafu(display(
+        "Metadata '{}' of column '{}' collides with column '{}'",
+        key,
+        name,
+        other
+    ))]
+    MetadataCollision {
+        key: String,
+        name: String,
+        other: String,
+    },
+
+    #[snafu(display("Metadata '{}' given for column '{}' is reserved", key, name))]
+    ReservedMetadataKey { key: String, name: String },
+
+    #[snafu(display(
+        "Metadata '{}' of column '{}' is {}, expected {:?}",
+        key,
+        name,
+        value,
+        expected
+    ))]
+    MetadataMismatch {
+        key: String,
+        name: String,
+        value: String,
+        expected: Option<String>,
+    },
+
+    #[snafu(display("Missing value of tag '{}'", name))]
+    MissingTag { name: String },
+
+    #[snafu(display("Invalid filter: {}", source))]
+    InvalidFilter { source: DataFusionError },
+
+    #[snafu(display(
+        "Table '{}' has {} fields, exceeds the limit {}",
+        table,
+        field_num,
+        limit
+    ))]
+    TooManyFields {
+        table: String,
+        field_num: usize,
+        limit: usize,
+    },
+
+    #[snafu(display("Table '{}' has no time column", table))]
+    MissingTimeColumn { table: String },
+
+    #[snafu(display(
+        "Precision {} of table '{}' does not match precision {} of the database",
+        table_precision,
+        table,
+        db_precision
+    ))]
+    PrecisionMismatch {
+        table: String,
+        table_precision: Precision,
+        db_precision: Precision,
+    },
+
+    #[snafu(display("Tag key '{}' is not allowed in table '{}'", key, table))]
+    TagKeyNotAllowed { table: String, key: String },
+
+    #[snafu(display("Table '{}' has duplicate column id {}", table, id))]
+    DuplicateColumnId { table: String, id: ColumnId },
+
+    #[snafu(display("Table '{}' has duplicate column '{}'", table, name))]
+    DuplicateColumnName { table: String, name: String },
+
+    #[snafu(display(
+        "Table '{}' maps column '{}' to arrow position {}, expected {}",
+        table,
+        name,
+        position,
+        expected
+    ))]
+    ArrowPositionConflict {
+        table: String,
+        name: String,
+        position: usize,
+        expected: usize,
+    },
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum SchemaMergeError {
+    #[snafu(display(
+        "Table '{}' can't be merged, columns with conflicting types: {}",
+        table,
+        conflicts
+            .iter()
+            .map(|conflict| conflict.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))]
+    TypeConflicts {
+        table: String,
+        conflicts: Vec<MergeConflict>,
+    },
+}
+
+/// A column present in both schemas of a merge with different types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub name: String,
+    pub left_type: ColumnType,
+    pub right_type: ColumnType,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum TableSchema {
     TsKvTableSchema(TskvTableSchema),
@@ -122,6 +265,12 @@ pub struct TskvTableSchema {
     columns: Vec<TableColumn>,
     //ColumnName -> ColumnsIndex
     columns_index: HashMap<String, usize>,
+    /// Precision of the time column, the database precision if not set
+    #[serde(default)]
+    pub precision: Option<Precision>,
+    /// Tag keys which may be written to the table, any key if not set
+    #[serde(default)]
+    pub allowed_tag_keys: Option<Vec<String>>,
 }
 
 impl Default for TskvTableSchema {
@@ -133,17 +282,229 @@ impl Default for TskvTableSchema {
             next_column_id: 0,
             columns: Default::default(),
             columns_index: Default::default(),
+            precision: None,
+            allowed_tag_keys: None,
         }
     }
 }
 
 impl TskvTableSchema {
+    /// Arrow schema of the columns, soft-deleted columns are skipped
     pub fn to_arrow_schema(&self) -> SchemaRef {
+        let fields: Vec<ArrowField> = self
+            .columns
+            .iter()
+            .filter(|column| !column.deleted)
+            .map(|field| field.into())
+            .collect();
+
+        Arc::new(Schema::new(fields))
+    }
+
+    /// Arrow schema of all columns, including the soft-deleted ones
+    pub fn to_arrow_schema_include_deleted(&self) -> SchemaRef {
         let fields: Vec<ArrowField> = self.columns.iter().map(|field| field.into()).collect();
 
         Arc::new(Schema::new(fields))
     }
 
+    /// Arrow fields paired with their position in `to_arrow_schema`, which
+    /// is the same as `column_index` as long as no column is soft-deleted.
+    pub fn indexed_arrow_fields(&self) -> Vec<(usize, ArrowField)> {
+        self.columns
+            .iter()
+            .filter(|column| !column.deleted)
+            .map(ArrowField::from)
+            .enumerate()
+            .collect()
+    }
+
+    /// Arrow schema for Parquet export, each field is annotated with its Parquet
+    /// logical type. Soft-deleted columns are skipped.
+    pub fn parquet_export_schema(&self) -> SchemaRef {
+        let fields: Vec<ArrowField> = self
+            .columns
+            .iter()
+            .filter(|column| !column.deleted)
+            .map(Self::parquet_export_field)
+            .collect();
+
+        Arc::new(Schema::new(fields))
+    }
+
+    fn parquet_export_field(column: &TableColumn) -> ArrowField {
+        let mut field = ArrowField::from(column);
+        if let Some(logical_type) = column.column_type.to_parquet_logical_type() {
+            let mut metadata = field.metadata().cloned().unwrap_or_default();
+            metadata.insert(PARQUET_LOGICAL_TYPE.to_string(), logical_type.to_string());
+            field.set_metadata(Some(metadata));
+        }
+        field
+    }
+
+    /// Arrow field of the time column, with the timestamp unit of `precision`
+    pub fn time_arrow_field(&self, precision: Precision) -> Option<ArrowField> {
+        let column = self
+            .columns
+            .iter()
+            .find(|column| column.column_type.is_time() && !column.deleted)?;
+        let field = ArrowField::from(column);
+        Some(
+            ArrowField::new(
+                field.name(),
+                ArrowDataType::Timestamp(precision.time_unit(), None),
+                field.is_nullable(),
+            )
+            .with_metadata(field.metadata().cloned()),
+        )
+    }
+
+    /// `to_arrow_schema` with the columns in `aliases` renamed from key to value,
+    /// the field metadata is kept. Error if an alias names an unknown column.
+    pub fn aliased_arrow_schema(
+        &self,
+        aliases: &BTreeMap<String, String>,
+    ) -> Result<SchemaRef, SchemaError> {
+        if let Some(name) = aliases
+            .keys()
+            .find(|name| self.column(name).map_or(true, |column| column.deleted))
+        {
+            return Err(SchemaError::ColumnNotFound { name: name.clone() });
+        }
+
+        let fields: Vec<ArrowField> = self
+            .to_arrow_schema()
+            .fields()
+            .iter()
+            .map(|field| match aliases.get(field.name()) {
+                Some(alias) => {
+                    ArrowField::new(alias, field.data_type().clone(), field.is_nullable())
+                        .with_metadata(field.metadata().cloned())
+                }
+                None => field.clone(),
+            })
+            .collect();
+
+        Ok(Arc::new(Schema::new(fields)))
+    }
+
+    /// Empty statistics of each column in `to_arrow_schema` order, for the storage
+    /// layer to fill in. Unknown counts and bounds are None.
+    pub fn column_statistics_template(&self) -> Vec<ColumnStatistics> {
+        self.columns
+            .iter()
+            .filter(|column| !column.deleted)
+            .map(|_| ColumnStatistics::default())
+            .collect()
+    }
+
+    /// Get the arrow field of the column, built the same way as in `to_arrow_schema`.
+    /// None for soft-deleted columns, like `to_arrow_schema` skips them.
+    pub fn arrow_field(&self, name: &str) -> Option<ArrowField> {
+        self.column(name)
+            .filter(|column| !column.deleted)
+            .map(|column| column.into())
+    }
+
+    /// Keys of the arrow field metadata written by `to_arrow_schema`
+    pub fn reserved_metadata_keys() -> &'static [&'static str] {
+        &RESERVED_METADATA_KEYS
+    }
+
+    /// Get the arrow field of the column with user metadata merged in,
+    /// error if the user metadata uses a reserved key.
+    pub fn arrow_field_with_metadata(
+        &self,
+        name: &str,
+        user_metadata: &BTreeMap<String, String>,
+    ) -> Result<ArrowField, SchemaError> {
+        let mut field = self
+            .arrow_field(name)
+            .ok_or_else(|| SchemaError::ColumnNotFound {
+                name: name.to_string(),
+            })?;
+        Self::check_user_metadata(name, user_metadata)?;
+
+        let mut metadata = field.metadata().cloned().unwrap_or_default();
+        metadata.extend(user_metadata.clone());
+        field.set_metadata(Some(metadata));
+        Ok(field)
+    }
+
+    /// Check user metadata, keyed by column name, before it is merged into the arrow fields
+    /// by `arrow_field_with_metadata`: error if a column is unknown or soft-deleted, or if
+    /// its metadata uses a reserved key.
+    pub fn validate_no_metadata_collision(
+        &self,
+        user_metadata: &BTreeMap<String, BTreeMap<String, String>>,
+    ) -> Result<(), SchemaError> {
+        for (name, metadata) in user_metadata {
+            if self.arrow_field(name).is_none() {
+                return Err(SchemaError::ColumnNotFound { name: name.clone() });
+            }
+            Self::check_user_metadata(name, metadata)?;
+        }
+        Ok(())
+    }
+
+    fn check_user_metadata(
+        name: &str,
+        user_metadata: &BTreeMap<String, String>,
+    ) -> Result<(), SchemaError> {
+        match Self::reserved_metadata_keys()
+            .iter()
+            .find(|key| user_metadata.contains_key(**key))
+        {
+            Some(key) => Err(SchemaError::ReservedMetadataKey {
+                key: key.to_string(),
+                name: name.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the reserved metadata of arrow fields built from the schema, possibly with
+    /// user metadata merged in: no two columns carry the same `_field_id`, and every
+    /// reserved key holds the value the schema writes for the column.
+    pub fn validate_arrow_metadata(&self, arrow_schema: &Schema) -> Result<(), SchemaError> {
+        let mut field_ids: HashMap<&String, &String> = HashMap::new();
+        for field in arrow_schema.fields() {
+            let metadata = match field.metadata() {
+                Some(metadata) => metadata,
+                None => continue,
+            };
+            let expected = self
+                .column(field.name())
+                .map(Self::parquet_export_field)
+                .and_then(|expected| expected.metadata().cloned());
+            for key in Self::reserved_metadata_keys() {
+                let value = match metadata.get(*key) {
+                    Some(value) => value,
+                    None => continue,
+                };
+                if *key == FIELD_ID {
+                    if let Some(other) = field_ids.insert(value, field.name()) {
+                        return Err(SchemaError::MetadataCollision {
+                            key: key.to_string(),
+                            name: field.name().clone(),
+                            other: other.clone(),
+                        });
+                    }
+                }
+                let expected_value = expected.as_ref().and_then(|m| m.get(*key));
+                if expected_value != Some(value) {
+                    return Err(SchemaError::MetadataMismatch {
+                        key: key.to_string(),
+                        name: field.name().clone(),
+                        value: value.clone(),
+                        expected: expected_value.cloned(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn new(db: String, name: String, columns: Vec<TableColumn>) -> Self {
         let columns_index = columns
             .iter()
@@ -158,6 +519,8 @@ impl TskvTableSchema {
             next_column_id: columns.len() as ColumnId,
             columns,
             columns_index,
+            precision: None,
+            allowed_tag_keys: None,
         }
     }
 
@@ -178,13 +541,59 @@ impl TskvTableSchema {
         if let Some(id) = self.columns_index.get(col_name) {
             self.columns.remove(*id);
         }
-        let columns_index = self
+        self.rebuild_columns_index();
+    }
+
+    /// Make sure exactly one column named `time` remains, dropping duplicated time columns.
+    /// Error if a column of another type also claims the name, or if the columns named
+    /// `time` are not of `ColumnType::Time`.
+    pub fn dedupe_time_columns(&mut self) -> Result<(), SchemaError> {
+        let mut time_column: Option<&TableColumn> = None;
+        for column in self.columns.iter().filter(|c| c.name == TIME_FIELD_NAME) {
+            match time_column {
+                Some(exists) if exists.column_type != column.column_type => {
+                    return Err(SchemaError::ColumnTypeConflict {
+                        name: column.name.clone(),
+                        left_type: exists.column_type,
+                        right_type: column.column_type,
+                    });
+                }
+                Some(_) => {}
+                None => time_column = Some(column),
+            }
+        }
+        if let Some(column) = time_column.filter(|c| !c.column_type.is_time()) {
+            return Err(SchemaError::NotTimeColumn {
+                name: column.name.clone(),
+                column_type: column.column_type,
+            });
+        }
+
+        let mut seen = false;
+        self.columns.retain(|column| {
+            if column.name != TIME_FIELD_NAME {
+                return true;
+            }
+            !std::mem::replace(&mut seen, true)
+        });
+        self.rebuild_columns_index();
+        Ok(())
+    }
+
+    fn rebuild_columns_index(&mut self) {
+        self.columns_index = self
             .columns
             .iter()
             .enumerate()
             .map(|(idx, e)| (e.name.clone(), idx))
             .collect();
-        self.columns_index = columns_index;
+    }
+
+    /// Mark the column as deleted but keep it in the schema, so the drop can be audited or reverted
+    pub fn soft_delete_field(&mut self, name: &str) {
+        if let Some(idx) = self.columns_index.get(name) {
+            self.columns[*idx].deleted = true;
+        }
     }
 
     pub fn change_column(&mut self, col_name: &str, new_column: TableColumn) {
@@ -196,6 +605,66 @@ impl TskvTableSchema {
         self.columns[id] = new_column;
     }
 
+    /// Expand the projection with the tag and time columns needed to rebuild series,
+    /// the result is deduplicated and follows the column order of the schema.
+    /// Soft-deleted columns are not found and never added.
+    pub fn expand_projection_for_scan(
+        &self,
+        requested: &[String],
+    ) -> Result<Vec<String>, SchemaError> {
+        for name in requested {
+            if !self.column(name).map_or(false, |column| !column.deleted) {
+                return Err(SchemaError::ColumnNotFound { name: name.clone() });
+            }
+        }
+
+        Ok(self
+            .columns
+            .iter()
+            .filter(|column| !column.deleted)
+            .filter(|column| !column.column_type.is_field() || requested.contains(&column.name))
+            .map(|column| column.name.clone())
+            .collect())
+    }
+
+    /// Arrow schema the storage has to scan: the projected columns, the columns referenced
+    /// by filters and the tag and time columns identifying series.
+    pub fn scan_schema(
+        &self,
+        projection: &[String],
+        filters: &[Expr],
+    ) -> Result<SchemaRef, SchemaError> {
+        let mut filter_columns = HashSet::new();
+        for filter in filters {
+            expr_to_columns(filter, &mut filter_columns)
+                .map_err(|source| SchemaError::InvalidFilter { source })?;
+        }
+
+        let mut required = projection.to_vec();
+        required.extend(filter_columns.into_iter().map(|column| column.name));
+        let fields = self
+            .expand_projection_for_scan(&required)?
+            .iter()
+            .filter_map(|name| self.arrow_field(name))
+            .collect();
+
+        Ok(Arc::new(Schema::new(fields)))
+    }
+
+    /// Arrow schema of a write batch: the columns present in the batch and the
+    /// tag and time columns, columns without any value are left out.
+    pub fn write_schema(&self, present_columns: &[String]) -> Result<SchemaRef, SchemaError> {
+        let fields = self
+            .expand_projection_for_scan(present_columns)?
+            .iter()
+            .filter_map(|name| self.column(name))
+            .filter(|column| !column.deleted)
+            .map(ArrowField::from)
+            .collect();
+
+        Ok(Arc::new(Schema::new(fields)))
+    }
+
     /// Get the metadata of the column according to the column name
     pub fn column(&self, name: &str) -> Option<&TableColumn> {
         self.columns_index
@@ -203,6 +672,49 @@ impl TskvTableSchema {
             .map(|idx| unsafe { self.columns.get_unchecked(*idx) })
     }
 
+    /// Get the type of the column according to the column name
+    pub fn column_type(&self, name: &str) -> Option<ColumnType> {
+        self.column(name).map(|column| column.column_type)
+    }
+
+    /// Map of column name to column type, soft-deleted columns are skipped
+    pub fn column_types(&self) -> BTreeMap<String, ColumnType> {
+        self.columns
+            .iter()
+            .filter(|column| !column.deleted)
+            .map(|column| (column.name.clone(), column.column_type))
+            .collect()
+    }
+
+    /// Number of columns of each type, keyed by `ColumnType::as_str`.
+    /// Soft-deleted columns are not counted.
+    pub fn type_histogram(&self) -> BTreeMap<String, usize> {
+        let mut histogram = BTreeMap::new();
+        for column in self.columns.iter().filter(|column| !column.deleted) {
+            *histogram
+                .entry(column.column_type.as_str().to_string())
+                .or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Id of the time column. The storage layer prunes files by the time range
+    /// recorded for this column, so it must identify the time column reliably.
+    pub fn time_column_id(&self) -> Option<ColumnId> {
+        self.columns
+            .iter()
+            .find(|column| column.column_type.is_time() && !column.deleted)
+            .map(|column| column.id)
+    }
+
+    /// Get the encoding of the time column, soft-deleted columns are skipped
+    pub fn time_codec(&self) -> Option<Encoding> {
+        self.columns
+            .iter()
+            .find(|column| column.column_type.is_time() && !column.deleted)
+            .map(|column| column.encoding)
+    }
+
     /// Get the index of the column
     pub fn column_index(&self, name: &str) -> Option<&usize> {
         self.columns_index.get(name)
@@ -226,7 +738,17 @@ impl TskvTableSchema {
         &self.columns
     }
 
+    /// Field columns, soft-deleted columns are skipped
     pub fn fields(&self) -> Vec<TableColumn> {
+        self.columns
+            .iter()
+            .filter(|column| column.column_type.is_field() && !column.deleted)
+            .cloned()
+            .collect()
+    }
+
+    /// Field columns, including the soft-deleted ones
+    pub fn fields_include_deleted(&self) -> Vec<TableColumn> {
         self.columns
             .iter()
             .filter(|column| column.column_type.is_field())
@@ -234,55 +756,753 @@ impl TskvTableSchema {
             .collect()
     }
 
-    /// Number of columns of ColumnType is Field
+    /// Columns which can be read as arrow dictionary arrays: tags and string fields
+    /// whose encoding allows dictionary encoding
+    pub fn dictionary_candidate_columns(&self) -> Vec<&TableColumn> {
+        self.columns
+            .iter()
+            .filter(|column| !column.deleted && column.encoding.is_dictionary_encoding())
+            .filter(|column| {
+                matches!(
+                    column.column_type,
+                    ColumnType::Tag | ColumnType::Field(ValueType::String)
+                )
+            })
+            .collect()
+    }
+
+    /// Field columns grouped by the byte of their encoding, so columns sharing an
+    /// encoding can be encoded together. Soft-deleted columns are skipped.
+    pub fn fields_by_codec(&self) -> BTreeMap<u8, Vec<&TableColumn>> {
+        let mut groups: BTreeMap<u8, Vec<&TableColumn>> = BTreeMap::new();
+        for column in self
+            .columns
+            .iter()
+            .filter(|column| column.column_type.is_field() && !column.deleted)
+        {
+            groups
+                .entry(column.encoding as u8)
+                .or_default()
+                .push(column);
+        }
+        groups
+    }
+
+    /// Number of columns of ColumnType is Field, soft-deleted columns are not counted
     pub fn field_num(&self) -> usize {
         self.columns
             .iter()
-            .filter(|column| column.column_type.is_field())
+            .filter(|column| column.column_type.is_field() && !column.deleted)
+            .count()
+    }
+
+    /// The value type shared by all field columns, None if they differ or there is no field
+    pub fn single_value_type(&self) -> Option<ValueType> {
+        let mut value_types = self.columns.iter().filter_map(|column| match column {
+            TableColumn {
+                column_type: ColumnType::Field(value_type),
+                deleted: false,
+                ..
+            } => Some(*value_type),
+            _ => None,
+        });
+        let first = value_types.next()?;
+        value_types
+            .all(|value_type| value_type == first)
+            .then_some(first)
+    }
+
+    /// Error if the number of field columns exceeds `max_fields`
+    pub fn check_field_limit(&self, max_fields: usize) -> Result<(), SchemaError> {
+        let field_num = self.field_num();
+        if field_num > max_fields {
+            return Err(SchemaError::TooManyFields {
+                table: self.name.clone(),
+                field_num,
+                limit: max_fields,
+            });
+        }
+        Ok(())
+    }
+
+    /// Names of the tag columns in ascending order
+    pub fn sorted_tag_keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self
+            .columns
+            .iter()
+            .filter(|column| column.column_type.is_tag() && !column.deleted)
+            .map(|column| column.name.as_str())
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Tag columns whose ids are not in `active_tag_ids`, candidates for cleanup.
+    /// Soft-deleted tags are skipped.
+    pub fn tags_not_in(&self, active_tag_ids: &[ColumnId]) -> Vec<&TableColumn> {
+        self.columns
+            .iter()
+            .filter(|column| column.column_type.is_tag() && !column.deleted)
+            .filter(|column| !active_tag_ids.contains(&column.id))
+            .collect()
+    }
+
+    /// Check the tag keys of a write against `allowed_tag_keys`
+    pub fn validate_tag_keys(&self, keys: &[String]) -> Result<(), SchemaError> {
+        let allowed = match &self.allowed_tag_keys {
+            Some(allowed) => allowed,
+            None => return Ok(()),
+        };
+        match keys.iter().find(|key| !allowed.contains(key)) {
+            Some(key) => Err(SchemaError::TagKeyNotAllowed {
+                table: self.name.clone(),
+                key: key.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Heuristic risk of series cardinality explosion, by the number of tags
+    /// of the table found in `high_card_tags`.
+    pub fn cardinality_risk(&self, high_card_tags: &[String]) -> CardinalityRisk {
+        let count = self
+            .sorted_tag_keys()
+            .into_iter()
+            .filter(|key| high_card_tags.iter().any(|tag| tag == key))
+            .count();
+        match count {
+            0 => CardinalityRisk::Low,
+            1 => CardinalityRisk::Medium,
+            _ => CardinalityRisk::High,
+        }
+    }
+
+    /// Build the series key `table,tag1=v1,tag2=v2` with tags in `sorted_tag_keys` order.
+    /// Commas, equal signs, spaces and percent signs are percent-escaped.
+    pub fn series_key(&self, tag_values: &BTreeMap<String, String>) -> Result<String, SchemaError> {
+        if let Some(name) = tag_values
+            .keys()
+            .find(|name| !matches!(self.column_type(name), Some(ColumnType::Tag)))
+        {
+            return Err(SchemaError::ColumnNotFound { name: name.clone() });
+        }
+
+        let mut key = escape_series_key_part(&self.name);
+        for tag_key in self.sorted_tag_keys() {
+            let tag_value = tag_values
+                .get(tag_key)
+                .ok_or_else(|| SchemaError::MissingTag {
+                    name: tag_key.to_string(),
+                })?;
+            key.push(',');
+            key.push_str(&escape_series_key_part(tag_key));
+            key.push('=');
+            key.push_str(&escape_series_key_part(tag_value));
+        }
+        Ok(key)
+    }
+
+    /// Number of tag columns, soft-deleted columns are not counted
+    pub fn tag_num(&self) -> usize {
+        self.columns
+            .iter()
+            .filter(|column| column.column_type.is_tag() && !column.deleted)
             .count()
     }
 
-    pub fn tag_num(&self) -> usize {
-        self.columns
-            .iter()
-            .filter(|column| column.column_type.is_tag())
-            .count()
+    // return (table_field_id, index), index mean field location which column
+    pub fn fields_id(&self) -> HashMap<ColumnId, usize> {
+        let mut ans = vec![];
+        for i in self.columns.iter() {
+            if i.column_type != ColumnType::Tag && i.column_type != ColumnType::Time {
+                ans.push(i.id);
+            }
+        }
+        ans.sort();
+        let mut map = HashMap::new();
+        for (i, id) in ans.iter().enumerate() {
+            map.insert(*id, i);
+        }
+        map
+    }
+
+    pub fn next_column_id(&mut self) -> ColumnId {
+        let ans = self.next_column_id;
+        self.next_column_id += 1;
+        ans
+    }
+
+    pub fn size(&self) -> usize {
+        let mut size = 0;
+        for i in self.columns.iter() {
+            size += size_of_val(&i);
+        }
+        size += size_of_val(&self);
+        size
+    }
+
+    pub fn contains_column(&self, column_name: &str) -> bool {
+        self.columns_index.contains_key(column_name)
+    }
+
+    /// Ordinals of the columns by ascending column id, 0 is reserved for the time column.
+    /// New columns get larger ids, so adding or soft-deleting a column never renumbers
+    /// the others, unlike positions in the arrow schema.
+    pub fn stable_ordinals(&self) -> BTreeMap<ColumnId, u32> {
+        let mut ids: Vec<ColumnId> = self
+            .columns
+            .iter()
+            .filter(|column| !column.column_type.is_time())
+            .map(|column| column.id)
+            .collect();
+        ids.sort_unstable();
+
+        let mut ordinals: BTreeMap<ColumnId, u32> = ids
+            .into_iter()
+            .enumerate()
+            .map(|(i, id)| (id, i as u32 + 1))
+            .collect();
+        if let Some(time) = self.columns.iter().find(|c| c.column_type.is_time()) {
+            ordinals.insert(time.id, 0);
+        }
+        ordinals
+    }
+
+    /// Replace `Encoding::Default`, which means the encoding is not set, with the
+    /// default encoding of the column type
+    pub fn resolve_default_codecs(&mut self) {
+        for column in self.columns.iter_mut() {
+            if column.encoding == Encoding::Default {
+                column.encoding = column.column_type.default_codec();
+            }
+        }
+    }
+
+    /// Dry-run of merging `other` into the schema: every column present in both
+    /// with different types is reported, the same conflicts `union_schemas` rejects.
+    pub fn can_merge(&self, other: &TskvTableSchema) -> Result<(), SchemaMergeError> {
+        let conflicts: Vec<MergeConflict> = other
+            .columns
+            .iter()
+            .filter_map(|column| {
+                let exists = self.column(&column.name)?;
+                (exists.column_type != column.column_type).then(|| MergeConflict {
+                    name: column.name.clone(),
+                    left_type: exists.column_type,
+                    right_type: column.column_type,
+                })
+            })
+            .collect();
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaMergeError::TypeConflicts {
+                table: self.name.clone(),
+                conflicts,
+            })
+        }
+    }
+
+    /// Schema-only description for `EXPLAIN`: a header line with the table and
+    /// schema id, then one line per column in ascending column id order.
+    /// Soft-deleted columns are left out.
+    pub fn explain(&self) -> String {
+        let mut columns: Vec<&TableColumn> = self
+            .columns
+            .iter()
+            .filter(|column| !column.deleted)
+            .collect();
+        columns.sort_by_key(|column| column.id);
+
+        let mut out = format!(
+            "table: {}.{}, schema_id: {}\n",
+            self.db, self.name, self.schema_id
+        );
+        for column in columns {
+            out.push_str(&format!(
+                "  {}: type={}, codec={}, id={}\n",
+                column.name,
+                column.column_type,
+                column.encoding.as_str(),
+                column.id
+            ));
+        }
+        out
+    }
+
+    /// Fingerprint of the arrow schema computed from our own canonical form instead of
+    /// arrow's representation: `(name, column type code, nullability)` of the columns
+    /// ordered by name. Soft-deleted columns are left out, like in `to_arrow_schema`.
+    pub fn stable_arrow_fingerprint(&self) -> u64 {
+        let mut columns: Vec<&TableColumn> = self
+            .columns
+            .iter()
+            .filter(|column| !column.deleted)
+            .collect();
+        columns.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut hasher = BkdrHasher::new();
+        for column in columns {
+            hasher.hash_with(column.name.as_bytes());
+            hasher.hash_with(&[0, column.column_type.to_proto(), column.nullable() as u8]);
+        }
+        hasher.number()
+    }
+
+    /// Flatten the schema into a catalog entry. Soft-deleted columns are kept and listed
+    /// in `deleted_columns`, so their ids are not reused after a round trip.
+    pub fn to_catalog_entry(&self) -> CatalogEntry {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| {
+                (
+                    column.name.clone(),
+                    column.column_type.to_proto(),
+                    column.encoding as u8,
+                    column.id as u64,
+                )
+            })
+            .collect();
+        let deleted_columns = self
+            .columns
+            .iter()
+            .filter(|column| column.deleted)
+            .map(|column| column.id as u64)
+            .collect();
+
+        CatalogEntry {
+            db: self.db.clone(),
+            name: self.name.clone(),
+            schema_id: self.schema_id,
+            columns,
+            deleted_columns,
+            next_column_id: self.next_column_id,
+            precision: self.precision.clone(),
+            allowed_tag_keys: self.allowed_tag_keys.clone(),
+        }
+    }
+
+    /// Check that the schema is internally consistent: it has a time column,
+    /// and column ids and names are unique.
+    pub fn validate(&self) -> Result<(), SchemaError> {
+        if self.time_column_id().is_none() {
+            return Err(SchemaError::MissingTimeColumn {
+                table: self.name.clone(),
+            });
+        }
+        let mut ids = HashSet::new();
+        let mut names = HashSet::new();
+        for column in self.columns.iter() {
+            if !ids.insert(column.id) {
+                return Err(SchemaError::DuplicateColumnId {
+                    table: self.name.clone(),
+                    id: column.id,
+                });
+            }
+            if !names.insert(column.name.as_str()) {
+                return Err(SchemaError::DuplicateColumnName {
+                    table: self.name.clone(),
+                    name: column.name.clone(),
+                });
+            }
+        }
+        self.assert_arrow_positions_unique()
+    }
+
+    /// Invariant check of `to_arrow_schema`: every non-time field name resolves to its
+    /// own position, so positions are unique and contiguous from 0.
+    pub fn assert_arrow_positions_unique(&self) -> Result<(), SchemaError> {
+        let schema = self.to_arrow_schema();
+        for (expected, field) in schema.fields().iter().enumerate() {
+            if field.name() == TIME_FIELD_NAME {
+                continue;
+            }
+            let position = schema.index_of(field.name()).unwrap_or(expected);
+            if position != expected {
+                return Err(SchemaError::ArrowPositionConflict {
+                    table: self.name.clone(),
+                    name: field.name().clone(),
+                    position,
+                    expected,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuild the schema from a catalog entry, new columns get ids after the stored
+    /// next column id and after the largest one in use.
+    /// Error if the rebuilt schema does not pass `validate`.
+    pub fn from_catalog_entry(entry: CatalogEntry) -> Result<Self, SchemaError> {
+        let columns: Vec<TableColumn> = entry
+            .columns
+            .into_iter()
+            .map(|(name, column_type, codec, id)| {
+                let mut column = TableColumn::new(
+                    id as ColumnId,
+                    name,
+                    ColumnType::from_proto(column_type),
+                    Encoding::from(codec),
+                );
+                column.deleted = entry.deleted_columns.contains(&id);
+                column
+            })
+            .collect();
+        let next_column_id = columns
+            .iter()
+            .map(|column| column.id + 1)
+            .max()
+            .unwrap_or(0)
+            .max(entry.next_column_id);
+
+        let mut schema = Self::new(entry.db, entry.name, columns);
+        schema.schema_id = entry.schema_id;
+        schema.next_column_id = next_column_id;
+        schema.precision = entry.precision;
+        schema.allowed_tag_keys = entry.allowed_tag_keys;
+        schema.validate()?;
+        Ok(schema)
+    }
+}
+
+/// Builds a `TskvTableSchema` from columns, assigning their ids
+pub struct TskvTableSchemaBuilder {
+    db: String,
+    name: String,
+    columns: Vec<TableColumn>,
+    deterministic_field_ids: bool,
+}
+
+impl TskvTableSchemaBuilder {
+    pub fn new(db: String, name: String) -> Self {
+        Self {
+            db,
+            name,
+            columns: vec![],
+            deterministic_field_ids: false,
+        }
+    }
+
+    /// Assign ids by sorted column name instead of insertion order,
+    /// so the ids don't depend on the order columns are added
+    pub fn with_deterministic_field_ids(mut self, deterministic: bool) -> Self {
+        self.deterministic_field_ids = deterministic;
+        self
+    }
+
+    /// Add a column, its id is assigned by `build`.
+    /// A column with the name of an already added column is ignored.
+    pub fn with_column(mut self, column: TableColumn) -> Self {
+        if !self.columns.iter().any(|c| c.name == column.name) {
+            self.columns.push(column);
+        }
+        self
+    }
+
+    pub fn build(self) -> TskvTableSchema {
+        let mut columns = self.columns;
+        if self.deterministic_field_ids {
+            let mut names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+            names.sort();
+            for column in columns.iter_mut() {
+                column.id = names.binary_search(&column.name).unwrap_or_default() as ColumnId;
+            }
+        } else {
+            for (id, column) in columns.iter_mut().enumerate() {
+                column.id = id as ColumnId;
+            }
+        }
+        TskvTableSchema::new(self.db, self.name, columns)
+    }
+}
+
+/// Flattened table schema stored by the catalog.
+/// Columns are `(name, column type code, encoding, id)`, the other fields keep what
+/// the columns can't carry: the ids of soft-deleted columns, the next column id so
+/// ids of dropped columns are not reused, and the table options. They default to
+/// empty when missing, so entries with only the columns can still be read.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CatalogEntry {
+    pub db: String,
+    pub name: String,
+    pub schema_id: SchemaId,
+    pub columns: Vec<(String, u8, u8, u64)>,
+    #[serde(default)]
+    pub deleted_columns: Vec<u64>,
+    #[serde(default)]
+    pub next_column_id: ColumnId,
+    #[serde(default)]
+    pub precision: Option<Precision>,
+    #[serde(default)]
+    pub allowed_tag_keys: Option<Vec<String>>,
+}
+
+/// Merge the columns of several tables into one superset schema.
+/// Columns are unioned by name in first-seen order and assigned fresh contiguous ids,
+/// the db and table name are taken from the first schema.
+pub fn union_schemas(schemas: &[&TskvTableSchema]) -> Result<TskvTableSchema, SchemaError> {
+    let (db, name) = match schemas.first() {
+        Some(schema) => (schema.db.clone(), schema.name.clone()),
+        None => return Ok(TskvTableSchema::default()),
+    };
+
+    let mut columns: Vec<TableColumn> = vec![];
+    let mut columns_index: HashMap<String, usize> = HashMap::new();
+    for schema in schemas {
+        for column in schema.columns() {
+            match columns_index.get(&column.name) {
+                Some(idx) => {
+                    let exists = &columns[*idx];
+                    if exists.column_type != column.column_type {
+                        return Err(SchemaError::ColumnTypeConflict {
+                            name: column.name.clone(),
+                            left_type: exists.column_type,
+                            right_type: column.column_type,
+                        });
+                    }
+                }
+                None => {
+                    columns_index.insert(column.name.clone(), columns.len());
+                    let mut column = column.clone();
+                    column.id = columns.len() as ColumnId;
+                    columns.push(column);
+                }
+            }
+        }
+    }
+
+    Ok(TskvTableSchema::new(db, name, columns))
+}
+
+/// JSON listing of tables for `SHOW TABLES`: an array of
+/// `{ db, name, schema_id, num_fields, num_tags }` sorted by db then name.
+/// External tables have no schema id and no tags.
+pub fn tables_to_json(schemas: &[TableSchema]) -> serde_json::Value {
+    let mut schemas: Vec<&TableSchema> = schemas.iter().collect();
+    schemas.sort_by_key(|schema| (schema.db(), schema.name()));
+
+    let tables = schemas
+        .into_iter()
+        .map(|schema| match schema {
+            TableSchema::TsKvTableSchema(schema) => serde_json::json!({
+                "db": schema.db,
+                "name": schema.name,
+                "schema_id": schema.schema_id,
+                "num_fields": schema.field_num(),
+                "num_tags": schema.tag_num(),
+            }),
+            TableSchema::ExternalTableSchema(schema) => serde_json::json!({
+                "db": schema.db,
+                "name": schema.name,
+                "schema_id": null,
+                "num_fields": schema.schema.fields().len(),
+                "num_tags": 0,
+            }),
+        })
+        .collect();
+    serde_json::Value::Array(tables)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CardinalityRisk {
+    /// No high-cardinality tag
+    Low,
+    /// A single high-cardinality tag
+    Medium,
+    /// Several high-cardinality tags, their combinations multiply the series count
+    High,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TypeChangeKind {
+    /// The type is not changed
+    Unchanged,
+    /// Every value of the old type can be represented by the new type
+    Widening,
+    /// Values may be truncated or fail to convert
+    Lossy,
+    /// The change is not allowed
+    Forbidden,
+}
+
+/// Classify an `ALTER COLUMN TYPE` from `old` to `new`.
+/// Only integer to float promotions are widening, other changes between field types
+/// are lossy. Changes between tag and field, or involving the time column, are forbidden.
+pub fn classify_type_change(old: ColumnType, new: ColumnType) -> TypeChangeKind {
+    if old == new {
+        return TypeChangeKind::Unchanged;
+    }
+    let (old, new) = match (old, new) {
+        (ColumnType::Field(old), ColumnType::Field(new)) => (old, new),
+        _ => return TypeChangeKind::Forbidden,
+    };
+    match (old, new) {
+        (ValueType::Unknown, _) | (_, ValueType::Unknown) => TypeChangeKind::Forbidden,
+        (ValueType::Integer | ValueType::Unsigned, ValueType::Float) => TypeChangeKind::Widening,
+        _ => TypeChangeKind::Lossy,
+    }
+}
+
+/// A metadata entry of an arrow field which differs between two schemas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMetadataChange {
+    pub field: String,
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Compare the field metadata of two arrow schemas, fields are matched by name.
+/// Metadata of added or removed fields is reported as changed from or to None.
+pub fn arrow_metadata_diff(a: &Schema, b: &Schema) -> Vec<FieldMetadataChange> {
+    let empty = BTreeMap::new();
+    let metadata_of = |schema: &Schema, name: &str| -> Option<BTreeMap<String, String>> {
+        schema
+            .field_with_name(name)
+            .ok()
+            .map(|field| field.metadata().cloned().unwrap_or_default())
+    };
+
+    let mut names: Vec<&String> = a.fields().iter().map(|field| field.name()).collect();
+    for field in b.fields() {
+        if !names.contains(&field.name()) {
+            names.push(field.name());
+        }
+    }
+
+    let mut changes = vec![];
+    for name in names {
+        let old = metadata_of(a, name);
+        let new = metadata_of(b, name);
+        let old = old.as_ref().unwrap_or(&empty);
+        let new = new.as_ref().unwrap_or(&empty);
+
+        let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let (old_value, new_value) = (old.get(key), new.get(key));
+            if old_value != new_value {
+                changes.push(FieldMetadataChange {
+                    field: name.clone(),
+                    key: key.clone(),
+                    old: old_value.cloned(),
+                    new: new_value.cloned(),
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// Field-level difference between two arrow schemas, fields are matched by name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArrowSchemaDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub type_changed: Vec<FieldTypeChange>,
+}
+
+impl ArrowSchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.type_changed.is_empty()
     }
+}
 
-    // return (table_field_id, index), index mean field location which column
-    pub fn fields_id(&self) -> HashMap<ColumnId, usize> {
-        let mut ans = vec![];
-        for i in self.columns.iter() {
-            if i.column_type != ColumnType::Tag && i.column_type != ColumnType::Time {
-                ans.push(i.id);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldTypeChange {
+    pub field: String,
+    pub old: ArrowDataType,
+    pub new: ArrowDataType,
+}
+
+/// Compare the fields of two arrow schemas, names follow the field order of
+/// `old` for removed and changed fields, and of `new` for added fields.
+pub fn arrow_schema_diff(old: &Schema, new: &Schema) -> ArrowSchemaDiff {
+    let mut diff = ArrowSchemaDiff::default();
+    for old_field in old.fields() {
+        match new.field_with_name(old_field.name()) {
+            Ok(new_field) => {
+                if old_field.data_type() != new_field.data_type() {
+                    diff.type_changed.push(FieldTypeChange {
+                        field: old_field.name().clone(),
+                        old: old_field.data_type().clone(),
+                        new: new_field.data_type().clone(),
+                    });
+                }
             }
+            Err(_) => diff.removed.push(old_field.name().clone()),
         }
-        ans.sort();
-        let mut map = HashMap::new();
-        for (i, id) in ans.iter().enumerate() {
-            map.insert(*id, i);
+    }
+    for new_field in new.fields() {
+        if old.field_with_name(new_field.name()).is_err() {
+            diff.added.push(new_field.name().clone());
         }
-        map
     }
+    diff
+}
 
-    pub fn next_column_id(&mut self) -> ColumnId {
-        let ans = self.next_column_id;
-        self.next_column_id += 1;
-        ans
-    }
+/// `(name, old codec, new codec)` of the columns present in both schemas whose encoding
+/// changed, in the column order of `old`. Soft-deleted columns are skipped.
+pub fn codec_changes(old: &TskvTableSchema, new: &TskvTableSchema) -> Vec<(String, u8, u8)> {
+    old.columns
+        .iter()
+        .filter(|column| !column.deleted)
+        .filter_map(|old_column| {
+            let new_column = new.column(&old_column.name).filter(|c| !c.deleted)?;
+            (old_column.encoding != new_column.encoding).then(|| {
+                (
+                    old_column.name.clone(),
+                    old_column.encoding as u8,
+                    new_column.encoding as u8,
+                )
+            })
+        })
+        .collect()
+}
 
-    pub fn size(&self) -> usize {
-        let mut size = 0;
-        for i in self.columns.iter() {
-            size += size_of_val(&i);
+fn escape_series_key_part(part: &str) -> String {
+    let mut escaped = String::with_capacity(part.len());
+    for c in part.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            ',' => escaped.push_str("%2C"),
+            '=' => escaped.push_str("%3D"),
+            ' ' => escaped.push_str("%20"),
+            _ => escaped.push(c),
         }
-        size += size_of_val(&self);
-        size
     }
+    escaped
+}
 
-    pub fn contains_column(&self, column_name: &str) -> bool {
-        self.columns_index.contains_key(column_name)
+/// Check that table `schema` can be created in a database with options `opts`:
+/// it must have a time column, and its precision, if set, must be the database precision.
+pub fn validate_table_for_database(
+    schema: &TskvTableSchema,
+    opts: &DatabaseOptions,
+) -> Result<(), SchemaError> {
+    if !schema
+        .columns()
+        .iter()
+        .any(|column| column.column_type.is_time())
+    {
+        return Err(SchemaError::MissingTimeColumn {
+            table: schema.name.clone(),
+        });
+    }
+    if let Some(table_precision) = &schema.precision {
+        let db_precision = opts.precision_or_default();
+        if table_precision != db_precision {
+            return Err(SchemaError::PrecisionMismatch {
+                table: schema.name.clone(),
+                table_precision: table_precision.clone(),
+                db_precision: db_precision.clone(),
+            });
+        }
     }
+    Ok(())
 }
 
 pub fn is_time_column(field: &ArrowField) -> bool {
@@ -295,6 +1515,8 @@ pub struct TableColumn {
     pub name: String,
     pub column_type: ColumnType,
     pub encoding: Encoding,
+    #[serde(default)]
+    pub deleted: bool,
 }
 
 impl From<&TableColumn> for ArrowField {
@@ -321,6 +1543,7 @@ impl TableColumn {
             name,
             column_type,
             encoding,
+            deleted: false,
         }
     }
     pub fn new_with_default(name: String, column_type: ColumnType) -> Self {
@@ -329,6 +1552,7 @@ impl TableColumn {
             name,
             column_type,
             encoding: Encoding::Default,
+            deleted: false,
         }
     }
 
@@ -338,6 +1562,7 @@ impl TableColumn {
             name: TIME_FIELD_NAME.to_string(),
             column_type: ColumnType::Time,
             encoding: Encoding::Default,
+            deleted: false,
         }
     }
 
@@ -347,6 +1572,7 @@ impl TableColumn {
             name,
             column_type: ColumnType::Tag,
             encoding: Encoding::Default,
+            deleted: false,
         }
     }
 
@@ -426,6 +1652,23 @@ impl ColumnType {
         }
     }
 
+    /// Code of the column type in serialized schemas, tags and time included
+    pub fn to_proto(&self) -> u8 {
+        match self {
+            Self::Time => 5,
+            Self::Tag => 6,
+            Self::Field(ValueType::Unknown) => 15,
+            Self::Field(_) => self.field_type(),
+        }
+    }
+
+    pub fn from_proto(code: u8) -> Self {
+        match code {
+            6 => Self::Tag,
+            _ => Self::from_i32(code as i32),
+        }
+    }
+
     pub fn from_i32(field_type: i32) -> Self {
         match field_type {
             0 => Self::Field(ValueType::Float),
@@ -452,6 +1695,61 @@ impl ColumnType {
             },
         }
     }
+
+    /// SQL type keyword of the column, as shown by `DESCRIBE TABLE`. This is
+    /// the same keyword `CREATE TABLE` accepts, so strings and tags are
+    /// `STRING` rather than `VARCHAR`.
+    pub fn sql_type_name(&self) -> &'static str {
+        self.to_sql_type_str()
+    }
+
+    /// Encoding used for the column when none is specified, the same one the
+    /// storage falls back to. Tags are not stored in data blocks, so they keep
+    /// `Encoding::Default`.
+    pub fn default_codec(&self) -> Encoding {
+        match self {
+            Self::Time => Encoding::Delta,
+            Self::Field(ValueType::Integer) | Self::Field(ValueType::Unsigned) => Encoding::Delta,
+            Self::Field(ValueType::Float) => Encoding::Gorilla,
+            Self::Field(ValueType::String) => Encoding::Snappy,
+            Self::Field(ValueType::Boolean) => Encoding::BitPack,
+            Self::Tag | Self::Field(ValueType::Unknown) => Encoding::Default,
+        }
+    }
+
+    /// Parquet logical type of the column, None if the physical type is enough
+    pub fn to_parquet_logical_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Tag => Some("STRING"),
+            Self::Time => Some("TIMESTAMP(NANOS,true)"),
+            Self::Field(ValueType::String) => Some("STRING"),
+            Self::Field(ValueType::Integer) => Some("INTEGER(64,true)"),
+            Self::Field(ValueType::Unsigned) => Some("INTEGER(64,false)"),
+            Self::Field(_) => None,
+        }
+    }
+
+    /// Field type inferred from a raw line protocol field value, None if the value is invalid.
+    /// An unquoted number without `i` or `u` suffix is an integer if it has no fraction,
+    /// unless `influx_compat` applies InfluxDB's rule that such numbers are always floats.
+    pub fn from_line_protocol(value: &str, influx_compat: bool) -> Option<ColumnType> {
+        let value_type = match value {
+            v if v.len() >= 2 && v.starts_with('"') && v.ends_with('"') => ValueType::String,
+            "t" | "T" | "true" | "True" | "TRUE" | "f" | "F" | "false" | "False" | "FALSE" => {
+                ValueType::Boolean
+            }
+            v if v.ends_with(['i', 'I']) && v[..v.len() - 1].parse::<i64>().is_ok() => {
+                ValueType::Integer
+            }
+            v if v.ends_with(['u', 'U']) && v[..v.len() - 1].parse::<u64>().is_ok() => {
+                ValueType::Unsigned
+            }
+            v if !influx_compat && v.parse::<i64>().is_ok() => ValueType::Integer,
+            v if v.parse::<f64>().is_ok() => ValueType::Float,
+            _ => return None,
+        };
+        Some(ColumnType::Field(value_type))
+    }
 }
 
 impl std::fmt::Display for ColumnType {
@@ -488,6 +1786,19 @@ impl DatabaseSchema {
             config: DatabaseOptions::default(),
         }
     }
+
+    /// The `CREATE DATABASE` statement of the database, with all options set to their effective value
+    pub fn to_create_sql(&self) -> String {
+        format!(
+            "CREATE DATABASE {} WITH TTL '{}' SHARD {} VNODE_DURATION '{}' REPLICA {} PRECISION '{}'",
+            self.name,
+            self.config.ttl_or_default().to_sql_string(),
+            self.config.shard_num_or_default(),
+            self.config.vnode_duration_or_default().to_sql_string(),
+            self.config.replica_or_default(),
+            self.config.precision_or_default(),
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
@@ -598,6 +1909,58 @@ impl Precision {
             _ => None,
         }
     }
+
+    /// Arrow timestamp unit of the precision
+    pub fn time_unit(&self) -> TimeUnit {
+        match self {
+            Precision::MS => TimeUnit::Millisecond,
+            Precision::US => TimeUnit::Microsecond,
+            Precision::NS => TimeUnit::Nanosecond,
+        }
+    }
+
+    /// Number of nanoseconds in one unit of the precision
+    pub fn nanos_per_unit(&self) -> i64 {
+        match self {
+            Precision::MS => 1_000_000,
+            Precision::US => 1_000,
+            Precision::NS => 1,
+        }
+    }
+
+    /// Convert a nanosecond timestamp to the precision, dropping the remainder by `rounding`
+    pub fn convert_nanos(&self, nanos: i64, rounding: PrecisionRounding) -> i64 {
+        let unit = self.nanos_per_unit();
+        let (quotient, remainder) = (nanos.div_euclid(unit), nanos.rem_euclid(unit));
+        match rounding {
+            PrecisionRounding::Truncate => nanos / unit,
+            PrecisionRounding::Round => quotient + (remainder * 2 >= unit) as i64,
+            PrecisionRounding::Ceil => quotient + (remainder > 0) as i64,
+        }
+    }
+}
+
+/// How the remainder is handled when a timestamp is coerced to a coarser precision
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrecisionRounding {
+    /// Drop the remainder, towards zero
+    #[default]
+    Truncate,
+    /// To the nearest unit, halves are rounded up
+    Round,
+    /// Towards positive infinity
+    Ceil,
+}
+
+impl PrecisionRounding {
+    pub fn new(text: &str) -> Option<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "truncate" => Some(Self::Truncate),
+            "round" => Some(Self::Round),
+            "ceil" => Some(Self::Ceil),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Precision {
@@ -634,6 +1997,16 @@ impl fmt::Display for Duration {
 }
 
 impl Duration {
+    /// Format as the duration literal of sql, e.g. `365d`, which can be parsed by `Duration::new`
+    pub fn to_sql_string(&self) -> String {
+        let unit = match self.unit {
+            DurationUnit::Minutes => "m",
+            DurationUnit::Hour => "h",
+            DurationUnit::Day => "d",
+        };
+        format!("{}{}", self.time_num, unit)
+    }
+
     // with default DurationUnit day
     pub fn new(text: &str) -> Option<Self> {
         if text.is_empty() {
@@ -667,3 +2040,1636 @@ impl Duration {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn new_schema(name: &str, columns: Vec<TableColumn>) -> TskvTableSchema {
+        TskvTableSchema::new("public".to_string(), name.to_string(), columns)
+    }
+
+    fn field_column(id: u32, name: &str, value_type: ValueType) -> TableColumn {
+        TableColumn::new(
+            id,
+            name.to_string(),
+            ColumnType::Field(value_type),
+            Encoding::Default,
+        )
+    }
+
+    #[test]
+    fn test_union_schemas() {
+        let a = new_schema(
+            "a",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "cpu", ValueType::Float),
+            ],
+        );
+        let b = new_schema(
+            "b",
+            vec![
+                TableColumn::new_time_column(5),
+                TableColumn::new_tag_column(3, "region".to_string()),
+                field_column(7, "cpu", ValueType::Float),
+                field_column(9, "mem", ValueType::Integer),
+            ],
+        );
+
+        let union = union_schemas(&[&a, &b]).unwrap();
+        assert_eq!(union.name, "a");
+        let names: Vec<&str> = union.columns().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["time", "host", "cpu", "region", "mem"]);
+        let ids: Vec<u32> = union.columns().iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+        assert_eq!(
+            union.column("mem").unwrap().column_type,
+            ColumnType::Field(ValueType::Integer)
+        );
+    }
+
+    #[test]
+    fn test_union_schemas_type_conflict() {
+        let a = new_schema("a", vec![field_column(0, "cpu", ValueType::Float)]);
+        let b = new_schema("b", vec![field_column(0, "cpu", ValueType::Integer)]);
+
+        let err = union_schemas(&[&a, &b]).unwrap_err();
+        assert!(matches!(err, SchemaError::ColumnTypeConflict { name, .. } if name == "cpu"));
+    }
+
+    #[test]
+    fn test_arrow_field() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+            ],
+        );
+
+        let arrow_schema = schema.to_arrow_schema();
+        for name in ["time", "host", "usage"] {
+            let field = schema.arrow_field(name).unwrap();
+            assert_eq!(&field, arrow_schema.field_with_name(name).unwrap());
+            assert_eq!(
+                field.metadata(),
+                arrow_schema.field_with_name(name).unwrap().metadata()
+            );
+        }
+        assert!(schema.arrow_field("not_exists").is_none());
+    }
+
+    #[test]
+    fn test_dedupe_time_columns() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+            ],
+        );
+        let expected = schema.clone();
+        schema.dedupe_time_columns().unwrap();
+        assert_eq!(schema, expected);
+
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, "usage", ValueType::Float),
+                TableColumn::new_time_column(2),
+            ],
+        );
+        schema.dedupe_time_columns().unwrap();
+        assert_eq!(schema.columns().len(), 2);
+        assert_eq!(schema.column(TIME_FIELD_NAME).unwrap().id, 0);
+        assert_eq!(schema.column_index("usage"), Some(&1));
+    }
+
+    #[test]
+    fn test_dedupe_time_columns_conflict() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, TIME_FIELD_NAME, ValueType::Integer),
+            ],
+        );
+        let err = schema.dedupe_time_columns().unwrap_err();
+        assert!(matches!(err, SchemaError::ColumnTypeConflict { .. }));
+    }
+
+    #[test]
+    fn test_dedupe_time_columns_not_time() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_tag_column(0, "host".to_string()),
+                field_column(1, TIME_FIELD_NAME, ValueType::Integer),
+            ],
+        );
+        let err = schema.dedupe_time_columns().unwrap_err();
+        assert!(matches!(err, SchemaError::NotTimeColumn { .. }));
+
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                field_column(0, TIME_FIELD_NAME, ValueType::Integer),
+                field_column(1, "usage", ValueType::Float),
+                field_column(2, TIME_FIELD_NAME, ValueType::Integer),
+            ],
+        );
+        let before = schema.clone();
+        let err = schema.dedupe_time_columns().unwrap_err();
+        assert!(matches!(err, SchemaError::NotTimeColumn { .. }));
+        assert_eq!(schema, before);
+    }
+
+    #[test]
+    fn test_column_types() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "count", ValueType::Unsigned),
+            ],
+        );
+
+        let types = schema.column_types();
+        assert_eq!(types.len(), 4);
+        assert_eq!(types["time"], ColumnType::Time);
+        assert_eq!(types["host"], ColumnType::Tag);
+        assert_eq!(types["usage"], ColumnType::Field(ValueType::Float));
+        assert_eq!(types["count"], ColumnType::Field(ValueType::Unsigned));
+
+        assert_eq!(schema.column_type("time"), Some(ColumnType::Time));
+        assert_eq!(schema.column_type("host"), Some(ColumnType::Tag));
+        assert_eq!(
+            schema.column_type("usage"),
+            Some(ColumnType::Field(ValueType::Float))
+        );
+        assert_eq!(schema.column_type("not_exists"), None);
+    }
+
+    #[test]
+    fn test_expand_projection_for_scan() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                TableColumn::new_tag_column(2, "region".to_string()),
+                field_column(3, "usage", ValueType::Float),
+                field_column(4, "idle", ValueType::Float),
+            ],
+        );
+
+        let projection = schema
+            .expand_projection_for_scan(&["idle".to_string(), "idle".to_string()])
+            .unwrap();
+        assert_eq!(projection, vec!["time", "host", "region", "idle"]);
+
+        let err = schema
+            .expand_projection_for_scan(&["not_exists".to_string()])
+            .unwrap_err();
+        assert!(matches!(err, SchemaError::ColumnNotFound { .. }));
+    }
+
+    #[test]
+    fn test_metadata_collision() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+            ],
+        );
+
+        let mut user_metadata = BTreeMap::new();
+        user_metadata.insert("unit".to_string(), "percent".to_string());
+        let mut columns_metadata = BTreeMap::new();
+        columns_metadata.insert("usage".to_string(), user_metadata.clone());
+        schema
+            .validate_no_metadata_collision(&columns_metadata)
+            .unwrap();
+
+        let mut reserved_metadata = user_metadata.clone();
+        reserved_metadata.insert(TAG.to_string(), "true".to_string());
+        columns_metadata.insert("host".to_string(), reserved_metadata);
+        let err = schema
+            .validate_no_metadata_collision(&columns_metadata)
+            .unwrap_err();
+        assert!(
+            matches!(err, SchemaError::ReservedMetadataKey { key, name } if key == TAG && name == "host")
+        );
+
+        let mut columns_metadata = BTreeMap::new();
+        columns_metadata.insert("not_exists".to_string(), user_metadata.clone());
+        let err = schema
+            .validate_no_metadata_collision(&columns_metadata)
+            .unwrap_err();
+        assert!(matches!(err, SchemaError::ColumnNotFound { .. }));
+
+        let field = schema
+            .arrow_field_with_metadata("usage", &user_metadata)
+            .unwrap();
+        let metadata = field.metadata().unwrap();
+        assert_eq!(metadata.get("unit"), Some(&"percent".to_string()));
+        assert_eq!(metadata.get(FIELD_ID), Some(&"2".to_string()));
+        assert_eq!(metadata.get(TAG), Some(&"false".to_string()));
+
+        user_metadata.insert(FIELD_ID.to_string(), "100".to_string());
+        let err = schema
+            .arrow_field_with_metadata("usage", &user_metadata)
+            .unwrap_err();
+        assert!(matches!(err, SchemaError::ReservedMetadataKey { key, .. } if key == FIELD_ID));
+
+        let mut user_metadata = BTreeMap::new();
+        user_metadata.insert(PARQUET_LOGICAL_TYPE.to_string(), "DATE".to_string());
+        let err = schema
+            .arrow_field_with_metadata("usage", &user_metadata)
+            .unwrap_err();
+        assert!(
+            matches!(err, SchemaError::ReservedMetadataKey { key, .. } if key == PARQUET_LOGICAL_TYPE)
+        );
+
+        let mut host = schema.arrow_field("host").unwrap();
+        let mut metadata = host.metadata().cloned().unwrap();
+        metadata.insert(TAG.to_string(), "false".to_string());
+        host.set_metadata(Some(metadata));
+        let arrow_schema = Schema::new(vec![schema.arrow_field("time").unwrap(), host]);
+        let err = schema.validate_arrow_metadata(&arrow_schema).unwrap_err();
+        assert!(matches!(
+            err,
+            SchemaError::MetadataMismatch { key, name, .. } if key == TAG && name == "host"
+        ));
+
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, "usage", ValueType::Float),
+                field_column(1, "idle", ValueType::Float),
+            ],
+        );
+        let err = schema
+            .validate_arrow_metadata(&schema.parquet_export_schema())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            SchemaError::MetadataCollision { name, other, .. } if name == "idle" && other == "usage"
+        ));
+    }
+
+    #[test]
+    fn test_reserved_metadata_keys() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "count", ValueType::Integer),
+            ],
+        );
+        for arrow_schema in [schema.to_arrow_schema(), schema.parquet_export_schema()] {
+            for field in arrow_schema.fields() {
+                for key in field.metadata().unwrap().keys() {
+                    assert!(
+                        TskvTableSchema::reserved_metadata_keys().contains(&key.as_str()),
+                        "{} is not reserved",
+                        key
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_soft_delete_field() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "idle", ValueType::Float),
+            ],
+        );
+        schema.soft_delete_field("idle");
+
+        let arrow_schema = schema.to_arrow_schema();
+        assert_eq!(arrow_schema.fields().len(), 3);
+        assert!(arrow_schema.field_with_name("idle").is_err());
+        assert_eq!(schema.fields().len(), 1);
+
+        assert!(schema.column("idle").unwrap().deleted);
+        assert_eq!(schema.columns().len(), 4);
+        assert_eq!(schema.fields_include_deleted().len(), 2);
+        assert!(schema
+            .to_arrow_schema_include_deleted()
+            .field_with_name("idle")
+            .is_ok());
+    }
+
+    fn schema_with_deleted_columns() -> TskvTableSchema {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                TableColumn::new_tag_column(2, "region".to_string()),
+                field_column(3, "usage", ValueType::Float),
+                field_column(4, "idle", ValueType::Float),
+            ],
+        );
+        schema.soft_delete_field("region");
+        schema.soft_delete_field("idle");
+        schema
+    }
+
+    #[test]
+    fn test_arrow_field_soft_deleted() {
+        let schema = schema_with_deleted_columns();
+        assert!(schema.arrow_field("usage").is_some());
+        assert!(schema.arrow_field("idle").is_none());
+        assert!(schema.arrow_field("region").is_none());
+    }
+
+    #[test]
+    fn test_expand_projection_for_scan_soft_deleted() {
+        let schema = schema_with_deleted_columns();
+        assert_eq!(
+            schema
+                .expand_projection_for_scan(&["usage".to_string()])
+                .unwrap(),
+            vec!["time", "host", "usage"]
+        );
+        assert!(matches!(
+            schema.expand_projection_for_scan(&["idle".to_string()]),
+            Err(SchemaError::ColumnNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_scan_schema_soft_deleted() {
+        let schema = schema_with_deleted_columns();
+        let scan = schema.scan_schema(&["usage".to_string()], &[]).unwrap();
+        let names: Vec<&str> = scan.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, vec!["time", "host", "usage"]);
+    }
+
+    #[test]
+    fn test_field_num_soft_deleted() {
+        let schema = schema_with_deleted_columns();
+        assert_eq!(schema.field_num(), 1);
+    }
+
+    #[test]
+    fn test_tag_num_soft_deleted() {
+        let schema = schema_with_deleted_columns();
+        assert_eq!(schema.tag_num(), 1);
+    }
+
+    #[test]
+    fn test_tags_not_in_soft_deleted() {
+        let schema = schema_with_deleted_columns();
+        let names: Vec<&str> = schema
+            .tags_not_in(&[])
+            .iter()
+            .map(|column| column.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["host"]);
+    }
+
+    #[test]
+    fn test_column_types_soft_deleted() {
+        let schema = schema_with_deleted_columns();
+        let types = schema.column_types();
+        assert_eq!(types.len(), 3);
+        assert!(!types.contains_key("region"));
+        assert!(!types.contains_key("idle"));
+    }
+
+    #[test]
+    fn test_time_column_id_soft_deleted() {
+        let mut schema = schema_with_deleted_columns();
+        assert_eq!(schema.time_column_id(), Some(0));
+        schema.soft_delete_field(TIME_FIELD_NAME);
+        assert_eq!(schema.time_column_id(), None);
+    }
+
+    #[test]
+    fn test_time_codec_soft_deleted() {
+        let mut schema = schema_with_deleted_columns();
+        assert!(schema.time_codec().is_some());
+        schema.soft_delete_field(TIME_FIELD_NAME);
+        assert_eq!(schema.time_codec(), None);
+    }
+
+    #[test]
+    fn test_database_to_create_sql() {
+        let schema = DatabaseSchema::new("test");
+        assert_eq!(
+            schema.to_create_sql(),
+            "CREATE DATABASE test WITH TTL '365d' SHARD 1 VNODE_DURATION '365d' REPLICA 1 PRECISION 'NS'"
+        );
+
+        let mut schema = DatabaseSchema::new("test");
+        schema.config.with_ttl(Duration::new("10d").unwrap());
+        schema.config.with_shard_num(5);
+        schema
+            .config
+            .with_vnode_duration(Duration::new("12h").unwrap());
+        schema.config.with_replica(3);
+        schema.config.with_precision(Precision::US);
+        assert_eq!(
+            schema.to_create_sql(),
+            "CREATE DATABASE test WITH TTL '10d' SHARD 5 VNODE_DURATION '12h' REPLICA 3 PRECISION 'US'"
+        );
+    }
+
+    #[test]
+    fn test_dictionary_candidate_columns() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "count", ValueType::Integer),
+                field_column(4, "status", ValueType::String),
+                TableColumn::new(
+                    5,
+                    "message".to_string(),
+                    ColumnType::Field(ValueType::String),
+                    Encoding::Zstd,
+                ),
+            ],
+        );
+
+        let names: Vec<&str> = schema
+            .dictionary_candidate_columns()
+            .iter()
+            .map(|column| column.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["host", "status"]);
+    }
+
+    #[test]
+    fn test_scan_schema() {
+        use datafusion::prelude::{col, lit};
+
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "idle", ValueType::Float),
+                field_column(4, "count", ValueType::Integer),
+            ],
+        );
+
+        let scan_schema = schema
+            .scan_schema(&["idle".to_string()], &[col("usage").gt(lit(1.0))])
+            .unwrap();
+        let names: Vec<&str> = scan_schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(names, vec!["time", "host", "usage", "idle"]);
+        assert_eq!(
+            scan_schema.field_with_name("usage").unwrap(),
+            &schema.arrow_field("usage").unwrap()
+        );
+
+        let err = schema
+            .scan_schema(&["idle".to_string()], &[col("not_exists").gt(lit(1.0))])
+            .unwrap_err();
+        assert!(matches!(err, SchemaError::ColumnNotFound { .. }));
+    }
+
+    #[test]
+    fn test_check_field_limit() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "idle", ValueType::Float),
+            ],
+        );
+
+        schema.check_field_limit(3).unwrap();
+        schema.check_field_limit(2).unwrap();
+        let err = schema.check_field_limit(1).unwrap_err();
+        assert!(matches!(
+            err,
+            SchemaError::TooManyFields {
+                field_num: 2,
+                limit: 1,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_single_value_type() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "idle", ValueType::Float),
+            ],
+        );
+        assert_eq!(schema.single_value_type(), Some(ValueType::Float));
+
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, "usage", ValueType::Float),
+                field_column(2, "count", ValueType::Integer),
+            ],
+        );
+        assert_eq!(schema.single_value_type(), None);
+
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+            ],
+        );
+        assert_eq!(schema.single_value_type(), None);
+    }
+
+    #[test]
+    fn test_classify_type_change() {
+        let field = ColumnType::Field;
+
+        assert_eq!(
+            classify_type_change(field(ValueType::Integer), field(ValueType::Integer)),
+            TypeChangeKind::Unchanged
+        );
+
+        assert_eq!(
+            classify_type_change(field(ValueType::Integer), field(ValueType::Float)),
+            TypeChangeKind::Widening
+        );
+        assert_eq!(
+            classify_type_change(field(ValueType::Unsigned), field(ValueType::Float)),
+            TypeChangeKind::Widening
+        );
+
+        assert_eq!(
+            classify_type_change(field(ValueType::Float), field(ValueType::Integer)),
+            TypeChangeKind::Lossy
+        );
+        assert_eq!(
+            classify_type_change(field(ValueType::Integer), field(ValueType::Unsigned)),
+            TypeChangeKind::Lossy
+        );
+        assert_eq!(
+            classify_type_change(field(ValueType::String), field(ValueType::Float)),
+            TypeChangeKind::Lossy
+        );
+        for (old, new) in [
+            (ValueType::Float, ValueType::String),
+            (ValueType::Integer, ValueType::String),
+            (ValueType::Boolean, ValueType::String),
+            (ValueType::Boolean, ValueType::Float),
+            (ValueType::Boolean, ValueType::Integer),
+            (ValueType::Float, ValueType::Boolean),
+        ] {
+            assert_eq!(
+                classify_type_change(field(old), field(new)),
+                TypeChangeKind::Lossy,
+                "{:?} -> {:?}",
+                old,
+                new
+            );
+        }
+
+        assert_eq!(
+            classify_type_change(ColumnType::Tag, field(ValueType::String)),
+            TypeChangeKind::Forbidden
+        );
+        assert_eq!(
+            classify_type_change(field(ValueType::String), ColumnType::Tag),
+            TypeChangeKind::Forbidden
+        );
+        assert_eq!(
+            classify_type_change(ColumnType::Time, field(ValueType::Integer)),
+            TypeChangeKind::Forbidden
+        );
+        assert_eq!(
+            classify_type_change(field(ValueType::Integer), ColumnType::Time),
+            TypeChangeKind::Forbidden
+        );
+    }
+
+    #[test]
+    fn test_series_key() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "region".to_string()),
+                TableColumn::new_tag_column(2, "host".to_string()),
+                field_column(3, "usage", ValueType::Float),
+            ],
+        );
+        assert_eq!(schema.sorted_tag_keys(), vec!["host", "region"]);
+
+        let mut tags = BTreeMap::new();
+        tags.insert("region".to_string(), "cn-north".to_string());
+        tags.insert("host".to_string(), "server1".to_string());
+        assert_eq!(
+            schema.series_key(&tags).unwrap(),
+            "cpu,host=server1,region=cn-north"
+        );
+
+        tags.insert("host".to_string(), "a,b=c d%".to_string());
+        assert_eq!(
+            schema.series_key(&tags).unwrap(),
+            "cpu,host=a%2Cb%3Dc%20d%25,region=cn-north"
+        );
+
+        tags.remove("region");
+        let err = schema.series_key(&tags).unwrap_err();
+        assert!(matches!(err, SchemaError::MissingTag { name } if name == "region"));
+
+        tags.insert("region".to_string(), "cn-north".to_string());
+        tags.insert("usage".to_string(), "1".to_string());
+        let err = schema.series_key(&tags).unwrap_err();
+        assert!(matches!(err, SchemaError::ColumnNotFound { name } if name == "usage"));
+    }
+
+    #[test]
+    fn test_arrow_metadata_diff() {
+        let with_codec = |field: ArrowField, codec: &str| {
+            let mut metadata = field.metadata().cloned().unwrap_or_default();
+            metadata.insert("_codec".to_string(), codec.to_string());
+            field.with_metadata(Some(metadata))
+        };
+        let columns = vec![
+            TableColumn::new_time_column(0),
+            field_column(1, "usage", ValueType::Float),
+        ];
+        let schema = new_schema("cpu", columns.clone());
+        let old = Schema::new(vec![
+            schema.arrow_field("time").unwrap(),
+            with_codec(schema.arrow_field("usage").unwrap(), "GORILLA"),
+        ]);
+        let new = Schema::new(vec![
+            schema.arrow_field("time").unwrap(),
+            with_codec(schema.arrow_field("usage").unwrap(), "QUANTILE"),
+        ]);
+        assert_eq!(
+            arrow_metadata_diff(&old, &new),
+            vec![FieldMetadataChange {
+                field: "usage".to_string(),
+                key: "_codec".to_string(),
+                old: Some("GORILLA".to_string()),
+                new: Some("QUANTILE".to_string()),
+            }]
+        );
+
+        let mut columns = columns;
+        columns.push(field_column(2, "idle", ValueType::Float));
+        let new = new_schema("cpu", columns).to_arrow_schema();
+        let old = schema.to_arrow_schema();
+        assert_eq!(
+            arrow_metadata_diff(&old, &new),
+            vec![
+                FieldMetadataChange {
+                    field: "idle".to_string(),
+                    key: FIELD_ID.to_string(),
+                    old: None,
+                    new: Some("2".to_string()),
+                },
+                FieldMetadataChange {
+                    field: "idle".to_string(),
+                    key: TAG.to_string(),
+                    old: None,
+                    new: Some("false".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_indexed_arrow_fields() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "idle", ValueType::Float),
+            ],
+        );
+        let arrow_schema = schema.to_arrow_schema();
+        let indexed = schema.indexed_arrow_fields();
+        assert_eq!(indexed.len(), arrow_schema.fields().len());
+        for (i, (idx, field)) in indexed.iter().enumerate() {
+            assert_eq!(*idx, i);
+            assert_eq!(field, arrow_schema.field(*idx));
+            assert_eq!(schema.column_index(field.name()), Some(idx));
+        }
+    }
+
+    #[test]
+    fn test_type_histogram() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                TableColumn::new_tag_column(2, "region".to_string()),
+                field_column(3, "usage", ValueType::Float),
+                field_column(4, "idle", ValueType::Float),
+                field_column(5, "count", ValueType::Integer),
+                field_column(6, "note", ValueType::String),
+            ],
+        );
+        schema.soft_delete_field("note");
+
+        let histogram = schema.type_histogram();
+        assert_eq!(histogram.len(), 4);
+        assert_eq!(histogram["time"], 1);
+        assert_eq!(histogram["tag"], 2);
+        assert_eq!(histogram["f64"], 2);
+        assert_eq!(histogram["i64"], 1);
+        assert!(!histogram.contains_key("string"));
+    }
+
+    #[test]
+    fn test_write_schema() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "idle", ValueType::Float),
+                field_column(4, "count", ValueType::Integer),
+            ],
+        );
+
+        let write_schema = schema
+            .write_schema(&["count".to_string(), "usage".to_string()])
+            .unwrap();
+        let names: Vec<&str> = write_schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(names, vec!["time", "host", "usage", "count"]);
+        assert_eq!(
+            write_schema.field_with_name("usage").unwrap(),
+            &schema.arrow_field("usage").unwrap()
+        );
+
+        let write_schema = schema.write_schema(&[]).unwrap();
+        assert_eq!(write_schema.fields().len(), 2);
+
+        assert!(matches!(
+            schema.write_schema(&["memory".to_string()]),
+            Err(SchemaError::ColumnNotFound { name }) if name == "memory"
+        ));
+    }
+
+    #[test]
+    fn test_cardinality_risk() {
+        let schema = new_schema(
+            "http",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                TableColumn::new_tag_column(2, "request_id".to_string()),
+                TableColumn::new_tag_column(3, "user_id".to_string()),
+                field_column(4, "latency", ValueType::Float),
+            ],
+        );
+
+        assert_eq!(schema.cardinality_risk(&[]), CardinalityRisk::Low);
+        assert_eq!(
+            schema.cardinality_risk(&["latency".to_string(), "session_id".to_string()]),
+            CardinalityRisk::Low
+        );
+        assert_eq!(
+            schema.cardinality_risk(&["request_id".to_string()]),
+            CardinalityRisk::Medium
+        );
+        assert_eq!(
+            schema.cardinality_risk(&["request_id".to_string(), "user_id".to_string()]),
+            CardinalityRisk::High
+        );
+    }
+
+    #[test]
+    fn test_parquet_export_schema() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "count", ValueType::Unsigned),
+            ],
+        );
+        let export_schema = schema.parquet_export_schema();
+        let logical_type = |name: &str| {
+            export_schema
+                .field_with_name(name)
+                .unwrap()
+                .metadata()
+                .and_then(|metadata| metadata.get(PARQUET_LOGICAL_TYPE).cloned())
+        };
+
+        let time = export_schema.field_with_name("time").unwrap();
+        assert!(!time.is_nullable());
+        assert_eq!(
+            time.data_type(),
+            &ArrowDataType::Timestamp(TimeUnit::Nanosecond, None)
+        );
+        assert_eq!(
+            logical_type("time").as_deref(),
+            Some("TIMESTAMP(NANOS,true)")
+        );
+        assert_eq!(logical_type("host").as_deref(), Some("STRING"));
+        assert_eq!(logical_type("usage"), None);
+        assert_eq!(logical_type("count").as_deref(), Some("INTEGER(64,false)"));
+
+        let usage = export_schema.field_with_name("usage").unwrap();
+        assert!(usage.is_nullable());
+        assert_eq!(
+            usage.metadata().unwrap().get(FIELD_ID).map(String::as_str),
+            Some("2")
+        );
+    }
+
+    #[test]
+    fn test_validate_table_for_database() {
+        let mut opts = DatabaseOptions::default();
+        opts.with_precision(Precision::MS);
+
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, "usage", ValueType::Float),
+            ],
+        );
+        validate_table_for_database(&schema, &opts).unwrap();
+
+        schema.precision = Some(Precision::MS);
+        validate_table_for_database(&schema, &opts).unwrap();
+
+        schema.precision = Some(Precision::NS);
+        assert!(matches!(
+            validate_table_for_database(&schema, &opts),
+            Err(SchemaError::PrecisionMismatch {
+                table_precision: Precision::NS,
+                db_precision: Precision::MS,
+                ..
+            })
+        ));
+
+        let schema = new_schema("cpu", vec![field_column(1, "usage", ValueType::Float)]);
+        assert!(matches!(
+            validate_table_for_database(&schema, &opts),
+            Err(SchemaError::MissingTimeColumn { table }) if table == "cpu"
+        ));
+    }
+
+    #[test]
+    fn test_fields_by_codec() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                TableColumn::new(
+                    2,
+                    "usage".to_string(),
+                    ColumnType::Field(ValueType::Float),
+                    Encoding::Gorilla,
+                ),
+                TableColumn::new(
+                    3,
+                    "idle".to_string(),
+                    ColumnType::Field(ValueType::Float),
+                    Encoding::Gorilla,
+                ),
+                TableColumn::new(
+                    4,
+                    "count".to_string(),
+                    ColumnType::Field(ValueType::Integer),
+                    Encoding::Delta,
+                ),
+            ],
+        );
+
+        let groups = schema.fields_by_codec();
+        let names = |codec: Encoding| -> Vec<&str> {
+            groups[&(codec as u8)]
+                .iter()
+                .map(|column| column.name.as_str())
+                .collect()
+        };
+        assert_eq!(groups.len(), 2);
+        assert_eq!(names(Encoding::Gorilla), vec!["usage", "idle"]);
+        assert_eq!(names(Encoding::Delta), vec!["count"]);
+    }
+
+    #[test]
+    fn test_arrow_schema_diff() {
+        let columns = vec![
+            TableColumn::new_time_column(0),
+            TableColumn::new_tag_column(1, "host".to_string()),
+            field_column(2, "usage", ValueType::Float),
+        ];
+        let old = new_schema("cpu", columns.clone()).to_arrow_schema();
+        assert!(arrow_schema_diff(&old, &old).is_empty());
+
+        let mut added = columns.clone();
+        added.push(field_column(3, "idle", ValueType::Float));
+        let new = new_schema("cpu", added).to_arrow_schema();
+        assert_eq!(
+            arrow_schema_diff(&old, &new),
+            ArrowSchemaDiff {
+                added: vec!["idle".to_string()],
+                ..Default::default()
+            }
+        );
+
+        let removed = columns[..2].to_vec();
+        let new = new_schema("cpu", removed).to_arrow_schema();
+        assert_eq!(
+            arrow_schema_diff(&old, &new),
+            ArrowSchemaDiff {
+                removed: vec!["usage".to_string()],
+                ..Default::default()
+            }
+        );
+
+        let mut changed = columns;
+        changed[2] = field_column(2, "usage", ValueType::Integer);
+        let new = new_schema("cpu", changed).to_arrow_schema();
+        assert_eq!(
+            arrow_schema_diff(&old, &new),
+            ArrowSchemaDiff {
+                type_changed: vec![FieldTypeChange {
+                    field: "usage".to_string(),
+                    old: ArrowDataType::Float64,
+                    new: ArrowDataType::Int64,
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_time_arrow_field() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, "usage", ValueType::Float),
+            ],
+        );
+
+        let field = schema.time_arrow_field(Precision::US).unwrap();
+        assert_eq!(field.name(), "time");
+        assert_eq!(
+            field.data_type(),
+            &ArrowDataType::Timestamp(TimeUnit::Microsecond, None)
+        );
+        assert!(!field.is_nullable());
+        assert_eq!(
+            schema.time_arrow_field(Precision::NS).as_ref(),
+            schema.arrow_field("time").as_ref()
+        );
+
+        let schema = new_schema("cpu", vec![field_column(1, "usage", ValueType::Float)]);
+        assert!(schema.time_arrow_field(Precision::MS).is_none());
+    }
+
+    #[test]
+    fn test_catalog_entry() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                TableColumn::new(
+                    2,
+                    "usage".to_string(),
+                    ColumnType::Field(ValueType::Float),
+                    Encoding::Gorilla,
+                ),
+                TableColumn::new(
+                    3,
+                    "count".to_string(),
+                    ColumnType::Field(ValueType::Unsigned),
+                    Encoding::Delta,
+                ),
+                field_column(4, "up", ValueType::Boolean),
+                field_column(5, "note", ValueType::String),
+                field_column(6, "load", ValueType::Integer),
+            ],
+        );
+        schema.schema_id = 7;
+
+        let entry = schema.to_catalog_entry();
+        assert_eq!(entry.db, "public");
+        assert_eq!(entry.name, "cpu");
+        assert_eq!(entry.schema_id, 7);
+        assert_eq!(
+            entry.columns[2],
+            ("usage".to_string(), 0, Encoding::Gorilla as u8, 2)
+        );
+        assert_eq!(TskvTableSchema::from_catalog_entry(entry).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_column_type_proto() {
+        for column_type in [
+            ColumnType::Tag,
+            ColumnType::Time,
+            ColumnType::Field(ValueType::Float),
+            ColumnType::Field(ValueType::Integer),
+            ColumnType::Field(ValueType::Unsigned),
+            ColumnType::Field(ValueType::Boolean),
+            ColumnType::Field(ValueType::String),
+            ColumnType::Field(ValueType::Unknown),
+        ] {
+            assert_eq!(ColumnType::from_proto(column_type.to_proto()), column_type);
+            if column_type.is_field() && column_type != ColumnType::Field(ValueType::Unknown) {
+                assert_eq!(column_type.to_proto(), column_type.field_type());
+            }
+        }
+    }
+
+    #[test]
+    fn test_catalog_entry_soft_deleted() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "idle", ValueType::Float),
+            ],
+        );
+        schema.soft_delete_field("idle");
+        schema.precision = Some(Precision::MS);
+        schema.allowed_tag_keys = Some(vec!["host".to_string()]);
+
+        let entry = schema.to_catalog_entry();
+        assert_eq!(entry.columns.len(), 4);
+        assert_eq!(entry.deleted_columns, vec![3]);
+
+        let mut rebuilt = TskvTableSchema::from_catalog_entry(entry).unwrap();
+        assert_eq!(rebuilt, schema);
+        assert!(rebuilt.column("idle").unwrap().deleted);
+        assert_eq!(rebuilt.precision, Some(Precision::MS));
+        assert_eq!(rebuilt.allowed_tag_keys, Some(vec!["host".to_string()]));
+        assert_eq!(rebuilt.next_column_id(), 4);
+    }
+
+    #[test]
+    fn test_stable_ordinals() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_tag_column(0, "host".to_string()),
+                field_column(1, "usage", ValueType::Float),
+                TableColumn::new_time_column(2),
+                field_column(3, "idle", ValueType::Float),
+            ],
+        );
+        let ordinals = schema.stable_ordinals();
+        assert_eq!(ordinals, BTreeMap::from([(0, 1), (1, 2), (2, 0), (3, 3)]));
+
+        let id = schema.next_column_id();
+        schema.add_column(field_column(id, "count", ValueType::Integer));
+        schema.soft_delete_field("usage");
+        let evolved = schema.stable_ordinals();
+        for (id, ordinal) in ordinals.iter() {
+            assert_eq!(evolved.get(id), Some(ordinal));
+        }
+        assert_eq!(evolved.get(&id), Some(&4));
+    }
+
+    #[test]
+    fn test_validate_tag_keys() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+            ],
+        );
+        let keys = vec!["host".to_string(), "region".to_string()];
+        schema.validate_tag_keys(&keys).unwrap();
+
+        schema.allowed_tag_keys = Some(vec!["host".to_string(), "region".to_string()]);
+        schema.validate_tag_keys(&keys).unwrap();
+        schema.validate_tag_keys(&[]).unwrap();
+
+        let keys = vec!["host".to_string(), "user_id".to_string()];
+        assert!(matches!(
+            schema.validate_tag_keys(&keys),
+            Err(SchemaError::TagKeyNotAllowed { key, .. }) if key == "user_id"
+        ));
+    }
+
+    #[test]
+    fn test_time_column_id() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_tag_column(0, "host".to_string()),
+                TableColumn::new_time_column(1),
+                field_column(2, "usage", ValueType::Float),
+            ],
+        );
+        assert_eq!(schema.time_column_id(), Some(1));
+
+        let schema = new_schema("cpu", vec![field_column(0, "usage", ValueType::Float)]);
+        assert_eq!(schema.time_column_id(), None);
+    }
+
+    #[test]
+    fn test_from_catalog_entry_validation() {
+        let entry = CatalogEntry {
+            db: "public".to_string(),
+            name: "cpu".to_string(),
+            schema_id: 1,
+            columns: vec![
+                ("time".to_string(), ColumnType::Time.to_proto(), 0, 0),
+                ("host".to_string(), ColumnType::Tag.to_proto(), 0, 1),
+                (
+                    "usage".to_string(),
+                    ColumnType::Field(ValueType::Float).to_proto(),
+                    0,
+                    2,
+                ),
+            ],
+            deleted_columns: vec![],
+            next_column_id: 3,
+            precision: None,
+            allowed_tag_keys: None,
+        };
+        let schema = TskvTableSchema::from_catalog_entry(entry.clone()).unwrap();
+        assert_eq!(schema.time_column_id(), Some(0));
+        assert_eq!(schema.column_type("host"), Some(ColumnType::Tag));
+
+        let mut missing_time = entry.clone();
+        missing_time.columns.remove(0);
+        assert!(matches!(
+            TskvTableSchema::from_catalog_entry(missing_time),
+            Err(SchemaError::MissingTimeColumn { table }) if table == "cpu"
+        ));
+
+        let mut duplicate_id = entry;
+        duplicate_id.columns[2].3 = 1;
+        assert!(matches!(
+            TskvTableSchema::from_catalog_entry(duplicate_id),
+            Err(SchemaError::DuplicateColumnId { id: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_tags_not_in() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                TableColumn::new_tag_column(2, "region".to_string()),
+                TableColumn::new_tag_column(3, "rack".to_string()),
+                field_column(4, "usage", ValueType::Float),
+            ],
+        );
+
+        let unused = schema.tags_not_in(&[1, 3]);
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].name, "region");
+
+        assert_eq!(schema.tags_not_in(&[]).len(), 3);
+        assert!(schema.tags_not_in(&[1, 2, 3]).is_empty());
+    }
+
+    #[test]
+    fn test_aliased_arrow_schema() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+            ],
+        );
+
+        let aliases = BTreeMap::from([("usage".to_string(), "u".to_string())]);
+        let aliased = schema.aliased_arrow_schema(&aliases).unwrap();
+        assert_eq!(aliased.fields().len(), 3);
+        assert!(aliased.field_with_name("usage").is_err());
+
+        let original = schema.arrow_field("usage").unwrap();
+        let field = aliased.field_with_name("u").unwrap();
+        assert_eq!(field.data_type(), original.data_type());
+        assert_eq!(field.metadata(), original.metadata());
+        assert_eq!(
+            field.metadata().unwrap().get(FIELD_ID).map(String::as_str),
+            Some("2")
+        );
+        assert_eq!(
+            aliased.field_with_name("host").unwrap(),
+            &schema.arrow_field("host").unwrap()
+        );
+
+        let aliases = BTreeMap::from([("idle".to_string(), "i".to_string())]);
+        assert!(matches!(
+            schema.aliased_arrow_schema(&aliases),
+            Err(SchemaError::ColumnNotFound { name }) if name == "idle"
+        ));
+    }
+
+    #[test]
+    fn test_column_statistics_template() {
+        let mut schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+                field_column(3, "idle", ValueType::Float),
+            ],
+        );
+        schema.soft_delete_field("idle");
+
+        let statistics = schema.column_statistics_template();
+        assert_eq!(statistics.len(), schema.to_arrow_schema().fields().len());
+        assert!(statistics
+            .iter()
+            .all(|s| s.null_count.is_none() && s.distinct_count.is_none()));
+    }
+
+    #[test]
+    fn test_resolve_default_codecs() {
+        let mut columns = vec![
+            TableColumn::new_with_default("time".to_string(), ColumnType::Time),
+            TableColumn::new_with_default("host".to_string(), ColumnType::Tag),
+            TableColumn::new_with_default("usage".to_string(), ColumnType::Field(ValueType::Float)),
+            TableColumn::new_with_default("note".to_string(), ColumnType::Field(ValueType::String)),
+            TableColumn::new(
+                4,
+                "count".to_string(),
+                ColumnType::Field(ValueType::Integer),
+                Encoding::Quantile,
+            ),
+        ];
+        for (id, column) in columns.iter_mut().enumerate() {
+            column.id = id as ColumnId;
+        }
+        let mut schema = new_schema("cpu", columns);
+        schema.resolve_default_codecs();
+
+        let encoding = |name: &str| schema.column(name).unwrap().encoding;
+        assert_eq!(encoding("time"), Encoding::Delta);
+        assert_eq!(encoding("host"), Encoding::Default);
+        assert_eq!(encoding("usage"), Encoding::Gorilla);
+        assert_eq!(encoding("note"), Encoding::Snappy);
+        assert_eq!(encoding("count"), Encoding::Quantile);
+    }
+
+    #[test]
+    fn test_sql_type_name() {
+        assert_eq!(ColumnType::Tag.sql_type_name(), "STRING");
+        assert_eq!(ColumnType::Time.sql_type_name(), "TIMESTAMP");
+        assert_eq!(
+            ColumnType::Field(ValueType::Float).sql_type_name(),
+            "DOUBLE"
+        );
+        assert_eq!(
+            ColumnType::Field(ValueType::Integer).sql_type_name(),
+            "BIGINT"
+        );
+        assert_eq!(
+            ColumnType::Field(ValueType::Unsigned).sql_type_name(),
+            "BIGINT UNSIGNED"
+        );
+        assert_eq!(
+            ColumnType::Field(ValueType::String).sql_type_name(),
+            "STRING"
+        );
+        assert_eq!(
+            ColumnType::Field(ValueType::Boolean).sql_type_name(),
+            "BOOLEAN"
+        );
+        assert_eq!(
+            ColumnType::Field(ValueType::Unknown).sql_type_name(),
+            ColumnType::Field(ValueType::Unknown).to_sql_type_str()
+        );
+    }
+
+    #[test]
+    fn test_can_merge() {
+        let a = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+            ],
+        );
+        let b = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "idle", ValueType::Float),
+            ],
+        );
+        a.can_merge(&b).unwrap();
+        assert!(union_schemas(&[&a, &b]).is_ok());
+
+        let c = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, "host", ValueType::String),
+                field_column(2, "usage", ValueType::Integer),
+            ],
+        );
+        let before = a.clone();
+        match a.can_merge(&c) {
+            Err(SchemaMergeError::TypeConflicts { table, conflicts }) => {
+                assert_eq!(table, "cpu");
+                assert_eq!(
+                    conflicts,
+                    vec![
+                        MergeConflict {
+                            name: "host".to_string(),
+                            left_type: ColumnType::Tag,
+                            right_type: ColumnType::Field(ValueType::String),
+                        },
+                        MergeConflict {
+                            name: "usage".to_string(),
+                            left_type: ColumnType::Field(ValueType::Float),
+                            right_type: ColumnType::Field(ValueType::Integer),
+                        },
+                    ]
+                );
+            }
+            Ok(()) => panic!("expected conflicts"),
+        }
+        assert_eq!(a, before);
+        assert!(union_schemas(&[&a, &c]).is_err());
+    }
+
+    #[test]
+    fn test_explain() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                field_column(2, "usage", ValueType::Float),
+                TableColumn::new_time_column(0),
+                TableColumn::new(1, "host".to_string(), ColumnType::Tag, Encoding::Default),
+                TableColumn::new(
+                    3,
+                    "idle".to_string(),
+                    ColumnType::Field(ValueType::Float),
+                    Encoding::Gorilla,
+                ),
+            ],
+        );
+
+        let explain = schema.explain();
+        let lines: Vec<&str> = explain.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "table: public.cpu, schema_id: 0");
+        assert!(lines[1].starts_with("  time: ") && lines[1].ends_with("id=0"));
+        assert!(lines[2].contains("host") && lines[2].contains("codec=DEFAULT, id=1"));
+        assert!(lines[3].contains("usage") && lines[3].contains("codec=DEFAULT, id=2"));
+        assert!(lines[4].contains("idle") && lines[4].contains("codec=GORILLA, id=3"));
+    }
+
+    #[test]
+    fn test_deterministic_field_ids() {
+        let build = |names: &[&str], deterministic: bool| {
+            let mut builder = TskvTableSchemaBuilder::new("public".to_string(), "cpu".to_string())
+                .with_deterministic_field_ids(deterministic);
+            for name in names {
+                builder = builder.with_column(field_column(0, name, ValueType::Float));
+            }
+            builder.with_column(TableColumn::new_time_column(0)).build()
+        };
+        let ids = |schema: &TskvTableSchema| -> BTreeMap<String, ColumnId> {
+            schema
+                .columns()
+                .iter()
+                .map(|column| (column.name.clone(), column.id))
+                .collect()
+        };
+
+        let a = build(&["usage", "idle", "system"], true);
+        let b = build(&["system", "usage", "idle"], true);
+        assert_eq!(ids(&a), ids(&b));
+        assert_eq!(a.column("idle").unwrap().id, 0);
+        assert_eq!(a.column("system").unwrap().id, 1);
+        assert_eq!(a.column("time").unwrap().id, 2);
+        assert_eq!(a.column("usage").unwrap().id, 3);
+
+        let a = build(&["usage", "idle", "system"], false);
+        let b = build(&["system", "usage", "idle"], false);
+        assert_ne!(ids(&a), ids(&b));
+        assert_eq!(a.column("usage").unwrap().id, 0);
+    }
+
+    #[test]
+    fn test_stable_arrow_fingerprint() {
+        let columns = || {
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+            ]
+        };
+        let a = new_schema("cpu", columns());
+        let b = new_schema("cpu", columns().into_iter().rev().collect());
+        assert_eq!(
+            a.stable_arrow_fingerprint(),
+            new_schema("cpu", columns()).stable_arrow_fingerprint()
+        );
+        assert_eq!(a.stable_arrow_fingerprint(), b.stable_arrow_fingerprint());
+
+        let c = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Integer),
+            ],
+        );
+        assert_ne!(a.stable_arrow_fingerprint(), c.stable_arrow_fingerprint());
+    }
+
+    #[test]
+    fn test_tables_to_json() {
+        let mut mem = TskvTableSchema::new(
+            "telegraf".to_string(),
+            "mem".to_string(),
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "used", ValueType::Unsigned),
+            ],
+        );
+        mem.schema_id = 3;
+        let cpu = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                TableColumn::new_tag_column(2, "region".to_string()),
+                field_column(3, "usage", ValueType::Float),
+                field_column(4, "idle", ValueType::Float),
+            ],
+        );
+
+        let json = tables_to_json(&[
+            TableSchema::TsKvTableSchema(mem),
+            TableSchema::TsKvTableSchema(cpu),
+        ]);
+        assert_eq!(
+            json,
+            serde_json::json!([
+                { "db": "public", "name": "cpu", "schema_id": 0, "num_fields": 2, "num_tags": 2 },
+                { "db": "telegraf", "name": "mem", "schema_id": 3, "num_fields": 1, "num_tags": 1 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_assert_arrow_positions_unique() {
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new_tag_column(1, "host".to_string()),
+                field_column(2, "usage", ValueType::Float),
+            ],
+        );
+        schema.assert_arrow_positions_unique().unwrap();
+        schema.validate().unwrap();
+
+        // `new` keeps the duplicated column, only the index entry is overwritten
+        let schema = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, "usage", ValueType::Float),
+                field_column(2, "usage", ValueType::Float),
+            ],
+        );
+        match schema.assert_arrow_positions_unique() {
+            Err(SchemaError::ArrowPositionConflict {
+                name,
+                position,
+                expected,
+                ..
+            }) => {
+                assert_eq!(name, "usage");
+                assert_eq!(position, 1);
+                assert_eq!(expected, 2);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(schema.validate().is_err());
+    }
+
+    #[test]
+    fn test_from_line_protocol() {
+        let float = Some(ColumnType::Field(ValueType::Float));
+        let integer = Some(ColumnType::Field(ValueType::Integer));
+
+        assert_eq!(ColumnType::from_line_protocol("42", false), integer);
+        assert_eq!(ColumnType::from_line_protocol("-42", false), integer);
+        assert_eq!(ColumnType::from_line_protocol("42", true), float);
+        assert_eq!(ColumnType::from_line_protocol("-42", true), float);
+        assert_ne!(
+            ColumnType::from_line_protocol("42", false),
+            ColumnType::from_line_protocol("42", true)
+        );
+
+        for influx_compat in [false, true] {
+            let infer = |value: &str| ColumnType::from_line_protocol(value, influx_compat);
+            assert_eq!(infer("42i"), integer);
+            assert_eq!(infer("42u"), Some(ColumnType::Field(ValueType::Unsigned)));
+            assert_eq!(infer("4.2"), float);
+            assert_eq!(infer("true"), Some(ColumnType::Field(ValueType::Boolean)));
+            assert_eq!(infer("\"42\""), Some(ColumnType::Field(ValueType::String)));
+            assert_eq!(infer("4x2"), None);
+        }
+    }
+
+    #[test]
+    fn test_codec_changes() {
+        let old = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                field_column(1, "usage", ValueType::Float),
+                field_column(2, "idle", ValueType::Float),
+            ],
+        );
+        assert!(codec_changes(&old, &old).is_empty());
+
+        let new = new_schema(
+            "cpu",
+            vec![
+                TableColumn::new_time_column(0),
+                TableColumn::new(
+                    1,
+                    "usage".to_string(),
+                    ColumnType::Field(ValueType::Float),
+                    Encoding::Gorilla,
+                ),
+                field_column(2, "idle", ValueType::Float),
+                TableColumn::new(
+                    3,
+                    "system".to_string(),
+                    ColumnType::Field(ValueType::Float),
+                    Encoding::Gorilla,
+                ),
+            ],
+        );
+        assert_eq!(
+            codec_changes(&old, &new),
+            vec![(
+                "usage".to_string(),
+                Encoding::Default as u8,
+                Encoding::Gorilla as u8
+            )]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::sync::{Arc, Mutex, Once};
 
 use once_cell::sync::Lazy;
-pub use tracing::{debug, error, info, instrument, trace, warn};
+pub use tracing::{debug, error, info, info_span, instrument, trace, warn, Instrument, Span};
 use tracing_appender::{non_blocking, non_blocking::WorkerGuard, rolling};
 use tracing_error::ErrorLayer;
 use tracing_subscriber::{
@@ -1,5 +1,16 @@
-use std::{fs::File, io::prelude::Read};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::prelude::Read,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
+use chrono::NaiveTime;
+use models::codec::Encoding;
+use models::schema::{Duration, PrecisionRounding};
 use serde::{Deserialize, Serialize};
 use trace::info;
 
@@ -11,15 +22,302 @@ pub struct Config {
     pub cache: CacheConfig,
     pub log: LogConfig,
     pub security: SecurityConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub write: WriteConfig,
+    #[serde(default)]
+    pub downsample: Vec<DownsampleRule>,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    #[serde(default)]
+    pub table_override: Vec<TableOverride>,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub access_rule: Vec<AccessRule>,
+    #[serde(default)]
+    pub compat: CompatConfig,
     pub reporting_disabled: Option<bool>,
 }
 
 impl Config {
-    pub fn override_by_env(&mut self) {
-        self.storage.override_by_env();
-        self.wal.override_by_env();
-        self.cache.override_by_env();
-        self.query.override_by_env();
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        self.storage.override_by_env(env)?;
+        self.wal.override_by_env(env)?;
+        self.cache.override_by_env(env)?;
+        self.query.override_by_env(env)?;
+        self.log.override_by_env(env)?;
+        self.admin.override_by_env(env)?;
+        self.write.override_by_env(env)?;
+        self.memory.override_by_env(env)?;
+        self.backup.override_by_env(env)?;
+        self.compat.override_by_env(env)?;
+        Ok(())
+    }
+
+    /// Storage parameters overridden for table `db.table`
+    pub fn table_override(&self, db: &str, table: &str) -> Option<&TableOverride> {
+        self.table_override
+            .iter()
+            .find(|o| o.db == db && o.table == table)
+    }
+
+    /// Permission of `client` on database `db` from the most specific matching access rule,
+    /// the earliest rule wins between equally specific ones. Everything is allowed if no rule
+    /// matches.
+    pub fn effective_permission(&self, client: &str, db: &str) -> Permission {
+        self.access_rule
+            .iter()
+            .rev()
+            .filter(|rule| rule.matches(client, db))
+            .max_by_key(|rule| rule.specificity())
+            .map(AccessRule::permission)
+            .unwrap_or(Permission::All)
+    }
+
+    /// Write and read back a small file in the storage directory and, if the WAL is
+    /// enabled, in the WAL directory, to verify they exist and are writable.
+    pub fn run_self_check(&self) -> Result<(), SelfCheckError> {
+        let mut dirs = vec![PathBuf::from(&self.storage.path)];
+        if self.wal.enabled {
+            dirs.push(PathBuf::from(&self.wal.path));
+        }
+        dirs.iter().try_for_each(|dir| self_check_dir(dir))
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.sections_validation().into_iter().collect()
+    }
+
+    /// Validate all sections, collecting every error and warning instead of stopping at the first
+    pub fn validation(&self) -> ConfigValidation {
+        let errors: Vec<ConfigError> = self
+            .sections_validation()
+            .into_iter()
+            .filter_map(|res| res.err())
+            .collect();
+        ConfigValidation {
+            ok: errors.is_empty(),
+            errors,
+            warnings: self.warnings(),
+        }
+    }
+
+    fn sections_validation(&self) -> Vec<Result<(), ConfigError>> {
+        vec![
+            self.query.validate(),
+            self.storage.validate(),
+            self.wal.validate(),
+            self.cache.validate(),
+            self.admin.validate(),
+            self.write.validate(),
+            self.downsample
+                .iter()
+                .try_for_each(DownsampleRule::validate),
+            self.memory.validate(),
+            self.backup.validate(),
+            self.access_rule.iter().try_for_each(AccessRule::validate),
+        ]
+    }
+
+    fn warnings(&self) -> Vec<ConfigWarning> {
+        let mut warnings = vec![];
+        if !self.wal.enabled {
+            warnings.push(ConfigWarning::new(
+                "wal.enabled",
+                "WAL is disabled, unflushed data will be lost on crash",
+            ));
+        }
+        warnings
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigError {
+    pub key: String,
+    pub reason: String,
+}
+
+impl ConfigError {
+    pub fn new(key: &str, reason: impl Into<String>) -> Self {
+        Self {
+            key: key.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration '{}': {}", self.key, self.reason)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Environment variables which override the values of the configuration file
+#[derive(Debug, Clone, Default)]
+pub struct EnvOverrides {
+    vars: HashMap<String, String>,
+}
+
+impl EnvOverrides {
+    pub fn new(vars: HashMap<String, String>) -> Self {
+        Self { vars }
+    }
+
+    /// Variables of the current process prefixed with `CNOSDB_`, generic names like
+    /// `MAX_SERVER_CONNECTIONS` set for other programs don't change the configuration
+    pub fn from_process() -> Self {
+        Self::new(
+            std::env::vars()
+                .filter(|(key, _)| key.starts_with("CNOSDB_"))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.vars.get(key).cloned()
+    }
+
+    /// Parse the variable `key`, a value which can't be parsed is reported as a `ConfigError`
+    pub fn parse<T>(&self, key: &str) -> Result<Option<T>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.vars.get(key) {
+            Some(value) => value
+                .parse::<T>()
+                .map(Some)
+                .map_err(|err| ConfigError::new(key, format!("'{}' is invalid: {}", value, err))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfCheckError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl SelfCheckError {
+    pub fn new(path: &Path, reason: impl Into<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for SelfCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Self-check of '{}' failed: {}",
+            self.path.display(),
+            self.reason
+        )
+    }
+}
+
+impl std::error::Error for SelfCheckError {}
+
+const SELF_CHECK_FILE: &str = ".self_check";
+const SELF_CHECK_CONTENT: &[u8] = b"cnosdb self-check";
+
+fn self_check_dir(dir: &Path) -> Result<(), SelfCheckError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|err| SelfCheckError::new(dir, format!("failed to create: {}", err)))?;
+
+    let path = dir.join(SELF_CHECK_FILE);
+    std::fs::write(&path, SELF_CHECK_CONTENT)
+        .map_err(|err| SelfCheckError::new(&path, format!("failed to write: {}", err)))?;
+    let content = std::fs::read(&path)
+        .map_err(|err| SelfCheckError::new(&path, format!("failed to read: {}", err)))?;
+    std::fs::remove_file(&path)
+        .map_err(|err| SelfCheckError::new(&path, format!("failed to remove: {}", err)))?;
+
+    if content != SELF_CHECK_CONTENT {
+        return Err(SelfCheckError::new(&path, "content read back differs"));
+    }
+    Ok(())
+}
+
+/// A valid but suspicious configuration item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigWarning {
+    pub key: String,
+    pub reason: String,
+}
+
+impl ConfigWarning {
+    pub fn new(key: &str, reason: impl Into<String>) -> Self {
+        Self {
+            key: key.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigValidation {
+    pub ok: bool,
+    pub errors: Vec<ConfigError>,
+    pub warnings: Vec<ConfigWarning>,
+}
+
+/// Validate the configuration file, reporting read and parse failures as errors
+pub fn validate_file(path: &str) -> ConfigValidation {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            return ConfigValidation {
+                ok: false,
+                errors: vec![ConfigError::new(
+                    "",
+                    format!("failed to read file: {}", err),
+                )],
+                warnings: vec![],
+            }
+        }
+    };
+    match toml::from_str::<Config>(&content) {
+        Ok(config) => config.validation(),
+        Err(err) => ConfigValidation {
+            ok: false,
+            errors: vec![ConfigError::new(
+                "",
+                format!("failed to parse file: {}", err),
+            )],
+            warnings: vec![],
+        },
+    }
+}
+
+/// Validate the configuration file and format the result as
+/// `{ "ok": bool, "errors": [...], "warnings": [...] }` for tooling
+pub fn validate_file_json(path: &str) -> Result<String, ConfigError> {
+    serde_json::to_string_pretty(&validate_file(path))
+        .map_err(|err| ConfigError::new("", format!("failed to format result: {}", err)))
+}
+
+/// Parse a daily time window in `"HH:MM-HH:MM"` format.
+/// The end may be earlier than the start, meaning the window spans midnight.
+pub fn parse_time_window(window: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = window.split_once('-')?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+    let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+    Some((start, end))
+}
+
+fn time_window_contains(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
     }
 }
 
@@ -28,6 +326,24 @@ pub struct QueryConfig {
     pub max_server_connections: u32,
     pub query_sql_limit: u64,
     pub write_sql_limit: u64,
+    pub max_write_lines: Option<u64>,
+    pub target_partitions: Option<usize>,
+    #[serde(default = "QueryConfig::default_database_name")]
+    pub default_database: String,
+    pub max_result_bytes: Option<u64>,
+    #[serde(default)]
+    pub case_insensitive_identifiers: bool,
+    #[serde(default = "QueryConfig::default_max_expr_depth")]
+    pub max_expr_depth: u32,
+    pub query_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub partial_results_on_timeout: bool,
+    pub allowed_statements: Option<Vec<String>>,
+    pub max_connections_per_db: Option<u32>,
+    #[serde(default = "QueryConfig::default_timezone_name")]
+    pub default_timezone: String,
+    #[serde(default = "QueryConfig::default_write_consistency")]
+    pub write_consistency: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,30 +355,348 @@ pub struct StorageConfig {
     pub compact_trigger: u32,
     pub max_compact_size: u64,
     pub strict_write: bool,
+    #[serde(default)]
+    pub read_only: bool,
+    pub read_only_window: Option<String>,
+    #[serde(default)]
+    pub time_codec_default: u8,
+    #[serde(default = "StorageConfig::default_background_threads")]
+    pub background_threads: u32,
+    #[serde(default)]
+    pub verify_checksums_on_read: bool,
+    pub max_fields_per_table: Option<usize>,
+    pub max_tags_per_series: Option<u32>,
+    #[serde(default = "StorageConfig::default_flush_clock")]
+    pub flush_clock: String,
+    #[serde(default)]
+    pub preload_tables: Vec<String>,
+    #[serde(default = "StorageConfig::default_engine")]
+    pub engine: String,
+    #[serde(default = "StorageConfig::default_auto_index_tags")]
+    pub auto_index_tags: bool,
+    #[serde(default = "StorageConfig::default_precision_rounding")]
+    pub precision_rounding: String,
+    #[serde(default)]
+    pub auto_prune_empty_db: bool,
+    #[serde(default = "StorageConfig::default_empty_db_grace_secs")]
+    pub empty_db_grace_secs: u64,
+    #[serde(default = "StorageConfig::default_startup_self_check")]
+    pub startup_self_check: bool,
+    #[serde(default)]
+    pub deterministic_field_ids: bool,
 }
 
 impl StorageConfig {
-    pub fn override_by_env(&mut self) {
-        if let Ok(path) = std::env::var("CNOSDB_APPLICATION_PATH") {
+    /// Half of the available CPUs, at least 1
+    fn default_background_threads() -> u32 {
+        let cpus = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        (cpus as u32 / 2).max(1)
+    }
+
+    fn default_flush_clock() -> String {
+        "monotonic".to_string()
+    }
+
+    fn default_engine() -> String {
+        "tsm".to_string()
+    }
+
+    fn default_auto_index_tags() -> bool {
+        true
+    }
+
+    fn default_precision_rounding() -> String {
+        "truncate".to_string()
+    }
+
+    fn default_empty_db_grace_secs() -> u64 {
+        24 * 60 * 60
+    }
+
+    fn default_startup_self_check() -> bool {
+        true
+    }
+
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(path) = env.get("CNOSDB_APPLICATION_PATH") {
             self.path = path;
         }
-        if let Ok(size) = std::env::var("CNOSDB_SUMMARY_MAX_SUMMARY_SIZE") {
-            self.max_summary_size = size.parse::<u64>().unwrap();
+        if let Some(size) = env.parse::<u64>("CNOSDB_SUMMARY_MAX_SUMMARY_SIZE")? {
+            self.max_summary_size = size;
+        }
+        if let Some(size) = env.parse::<u32>("CNOSDB_STORAGE_MAX_LEVEL")? {
+            self.max_level = size;
+        }
+        if let Some(size) = env.parse::<u64>("CNOSDB_STORAGE_BASE_FILE_SIZE")? {
+            self.base_file_size = size;
+        }
+        if let Some(size) = env.parse::<u32>("CNOSDB_STORAGE_COMPACT_TRIGGER")? {
+            self.compact_trigger = size;
+        }
+        if let Some(size) = env.parse::<u64>("CNOSDB_STORAGE_MAX_COMPACT_SIZE")? {
+            self.max_compact_size = size;
+        }
+        if let Some(size) = env.parse::<bool>("CNOSDB_STORAGE_STRICT_WRITE")? {
+            self.strict_write = size;
+        }
+        if let Some(read_only) = env.parse::<bool>("CNOSDB_STORAGE_READ_ONLY")? {
+            self.read_only = read_only;
+        }
+        if let Some(window) = env.get("CNOSDB_STORAGE_READ_ONLY_WINDOW") {
+            self.read_only_window = Some(window);
+        }
+        if let Some(codec) = env.parse::<u8>("CNOSDB_STORAGE_TIME_CODEC_DEFAULT")? {
+            self.time_codec_default = codec;
+        }
+        if let Some(threads) = env.parse::<u32>("CNOSDB_BACKGROUND_THREADS")? {
+            self.background_threads = threads;
+        }
+        if let Some(verify) = env.parse::<bool>("CNOSDB_VERIFY_CHECKSUMS")? {
+            self.verify_checksums_on_read = verify;
+        }
+        if let Some(size) = env.parse::<usize>("CNOSDB_STORAGE_MAX_FIELDS_PER_TABLE")? {
+            self.max_fields_per_table = Some(size);
+        }
+        if let Some(size) = env.parse::<u32>("CNOSDB_MAX_TAGS_PER_SERIES")? {
+            self.max_tags_per_series = Some(size);
+        }
+        if let Some(clock) = env.get("CNOSDB_STORAGE_FLUSH_CLOCK") {
+            self.flush_clock = clock;
+        }
+        if let Some(engine) = env.get("CNOSDB_STORAGE_ENGINE") {
+            self.engine = engine;
+        }
+        if let Some(auto_index) = env.parse::<bool>("CNOSDB_AUTO_INDEX_TAGS")? {
+            self.auto_index_tags = auto_index;
+        }
+        if let Some(rounding) = env.get("CNOSDB_STORAGE_PRECISION_ROUNDING") {
+            self.precision_rounding = rounding;
+        }
+        if let Some(prune) = env.parse::<bool>("CNOSDB_STORAGE_AUTO_PRUNE_EMPTY_DB")? {
+            self.auto_prune_empty_db = prune;
+        }
+        if let Some(secs) = env.parse::<u64>("CNOSDB_STORAGE_EMPTY_DB_GRACE_SECS")? {
+            self.empty_db_grace_secs = secs;
+        }
+        if let Some(check) = env.parse::<bool>("CNOSDB_STORAGE_STARTUP_SELF_CHECK")? {
+            self.startup_self_check = check;
+        }
+        if let Some(deterministic) = env.parse::<bool>("CNOSDB_STORAGE_DETERMINISTIC_FIELD_IDS")? {
+            self.deterministic_field_ids = deterministic;
+        }
+        if let Some(tables) = env.get("CNOSDB_STORAGE_PRELOAD_TABLES") {
+            self.preload_tables = tables
+                .split(',')
+                .map(|table| table.trim().to_string())
+                .filter(|table| !table.is_empty())
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Number of threads shared by compaction, flush and retention jobs
+    pub fn background_threads(&self) -> usize {
+        self.background_threads as usize
+    }
+
+    /// Whether every block read from disk verifies its checksum
+    pub fn verify_checksums_on_read(&self) -> bool {
+        self.verify_checksums_on_read
+    }
+
+    /// Encoding of the time column for new tables
+    pub fn time_codec_default(&self) -> Encoding {
+        Encoding::from(self.time_codec_default)
+    }
+
+    /// Maximum number of tags of a written point, unlimited if None
+    pub fn max_tags_per_series(&self) -> Option<u32> {
+        self.max_tags_per_series
+    }
+
+    /// Clock used to schedule flushes, unknown names are rejected by `validate`
+    pub fn flush_clock(&self) -> FlushClock {
+        FlushClock::new(&self.flush_clock).unwrap_or_default()
+    }
+
+    /// Whether tags are indexed on write, otherwise they are stored but
+    /// only indexed by an explicit command
+    pub fn auto_index_tags(&self) -> bool {
+        self.auto_index_tags
+    }
+
+    /// Whether `Config::run_self_check` is run before accepting traffic
+    pub fn startup_self_check(&self) -> bool {
+        self.startup_self_check
+    }
+
+    /// Whether new table schemas assign column ids by sorted column name instead of
+    /// insertion order, the default of `TskvTableSchemaBuilder::with_deterministic_field_ids`
+    pub fn deterministic_field_ids(&self) -> bool {
+        self.deterministic_field_ids
+    }
+
+    /// Whether databases without any table are dropped once `empty_db_grace` has passed
+    pub fn auto_prune_empty_db(&self) -> bool {
+        self.auto_prune_empty_db
+    }
+
+    /// Time a database may stay empty before it is pruned
+    pub fn empty_db_grace(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.empty_db_grace_secs)
+    }
+
+    /// Rounding of timestamps coerced to a coarser precision, unknown names are rejected
+    /// by `validate`
+    pub fn precision_rounding(&self) -> PrecisionRounding {
+        PrecisionRounding::new(&self.precision_rounding).unwrap_or_default()
+    }
+
+    /// Storage engine of the data files, unknown names are rejected by `validate`
+    pub fn engine(&self) -> StorageEngine {
+        StorageEngine::new(&self.engine).unwrap_or_default()
+    }
+
+    /// `(database, table)` pairs whose recent data is loaded into cache on startup,
+    /// malformed entries are skipped
+    pub fn preload_tables(&self) -> Vec<(String, String)> {
+        self.preload_tables
+            .iter()
+            .filter_map(|entry| parse_preload_table(entry))
+            .collect()
+    }
+
+    /// Whether writes are rejected at `now`, either globally or by the read-only window
+    pub fn is_read_only_at(&self, now: NaiveTime) -> bool {
+        if self.read_only {
+            return true;
+        }
+        match self.read_only_window.as_deref().and_then(parse_time_window) {
+            Some((start, end)) => time_window_contains(start, end, now),
+            None => false,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(window) = &self.read_only_window {
+            if parse_time_window(window).is_none() {
+                return Err(ConfigError::new(
+                    "storage.read_only_window",
+                    format!("'{}' is not in 'HH:MM-HH:MM' format", window),
+                ));
+            }
+        }
+        if !self.time_codec_default().is_timestamp_encoding() {
+            return Err(ConfigError::new(
+                "storage.time_codec_default",
+                format!(
+                    "{} is not a valid encoding of time column",
+                    self.time_codec_default
+                ),
+            ));
         }
-        if let Ok(size) = std::env::var("CNOSDB_STORAGE_MAX_LEVEL") {
-            self.max_level = size.parse::<u32>().unwrap();
+        if self.background_threads < 1 {
+            return Err(ConfigError::new(
+                "storage.background_threads",
+                "must be at least 1",
+            ));
         }
-        if let Ok(size) = std::env::var("CNOSDB_STORAGE_BASE_FILE_SIZE") {
-            self.base_file_size = size.parse::<u64>().unwrap();
+        if self.max_fields_per_table == Some(0) {
+            return Err(ConfigError::new(
+                "storage.max_fields_per_table",
+                "must be greater than 0",
+            ));
         }
-        if let Ok(size) = std::env::var("CNOSDB_STORAGE_COMPACT_TRIGGER") {
-            self.compact_trigger = size.parse::<u32>().unwrap();
+        if self.max_tags_per_series == Some(0) {
+            return Err(ConfigError::new(
+                "storage.max_tags_per_series",
+                "must be greater than 0",
+            ));
         }
-        if let Ok(size) = std::env::var("CNOSDB_STORAGE_MAX_COMPACT_SIZE") {
-            self.max_compact_size = size.parse::<u64>().unwrap();
+        if FlushClock::new(&self.flush_clock).is_none() {
+            return Err(ConfigError::new(
+                "storage.flush_clock",
+                format!("'{}' is not one of 'wall', 'monotonic'", self.flush_clock),
+            ));
         }
-        if let Ok(size) = std::env::var("CNOSDB_STORAGE_STRICT_WRITE") {
-            self.strict_write = size.parse::<bool>().unwrap();
+        if self.auto_prune_empty_db && self.empty_db_grace_secs == 0 {
+            return Err(ConfigError::new(
+                "storage.empty_db_grace_secs",
+                "must be greater than 0 when auto_prune_empty_db is enabled",
+            ));
+        }
+        if PrecisionRounding::new(&self.precision_rounding).is_none() {
+            return Err(ConfigError::new(
+                "storage.precision_rounding",
+                format!(
+                    "'{}' is not one of 'truncate', 'round', 'ceil'",
+                    self.precision_rounding
+                ),
+            ));
+        }
+        if StorageEngine::new(&self.engine).is_none() {
+            return Err(ConfigError::new(
+                "storage.engine",
+                format!("'{}' is not one of 'tsm', 'parquet'", self.engine),
+            ));
+        }
+        if let Some(entry) = self
+            .preload_tables
+            .iter()
+            .find(|entry| parse_preload_table(entry).is_none())
+        {
+            return Err(ConfigError::new(
+                "storage.preload_tables",
+                format!("'{}' is not in 'db.table' format", entry),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageEngine {
+    #[default]
+    Tsm,
+    Parquet,
+}
+
+impl StorageEngine {
+    pub fn new(text: &str) -> Option<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "tsm" => Some(Self::Tsm),
+            "parquet" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// Parse `db.table`, both parts must be non-empty
+fn parse_preload_table(entry: &str) -> Option<(String, String)> {
+    let (db, table) = entry.split_once('.')?;
+    if db.is_empty() || table.is_empty() || table.contains('.') {
+        return None;
+    }
+    Some((db.to_string(), table.to_string()))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushClock {
+    /// System time, follows clock adjustments
+    Wall,
+    /// Time since startup, not affected by clock skew
+    #[default]
+    Monotonic,
+}
+
+impl FlushClock {
+    pub fn new(text: &str) -> Option<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "wall" => Some(Self::Wall),
+            "monotonic" => Some(Self::Monotonic),
+            _ => None,
         }
     }
 }
@@ -72,18 +706,100 @@ pub struct WalConfig {
     pub enabled: bool,
     pub path: String,
     pub sync: bool,
+    #[serde(default)]
+    pub wal_per_database: bool,
+    #[serde(default = "WalConfig::default_compression")]
+    pub compression: String,
+    #[serde(default = "WalConfig::default_max_inflight_bytes")]
+    pub max_inflight_bytes: u64,
 }
 
 impl WalConfig {
-    pub fn override_by_env(&mut self) {
-        if let Ok(enabled) = std::env::var("CNOSDB_WAL_ENABLED") {
-            self.enabled = enabled.as_str() == "true";
+    fn default_compression() -> String {
+        "none".to_string()
+    }
+
+    fn default_max_inflight_bytes() -> u64 {
+        64 * 1024 * 1024
+    }
+
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(enabled) = env.parse::<bool>("CNOSDB_WAL_ENABLED")? {
+            self.enabled = enabled;
         }
-        if let Ok(path) = std::env::var("CNOSDB_WAL_PATH") {
+        if let Some(path) = env.get("CNOSDB_WAL_PATH") {
             self.path = path;
         }
-        if let Ok(sync) = std::env::var("CNOSDB_WAL_SYNC") {
-            self.sync = sync.as_str() == sync;
+        if let Some(sync) = env.parse::<bool>("CNOSDB_WAL_SYNC")? {
+            self.sync = sync;
+        }
+        if let Some(per_database) = env.parse::<bool>("CNOSDB_WAL_PER_DATABASE")? {
+            self.wal_per_database = per_database;
+        }
+        if let Some(compression) = env.get("CNOSDB_WAL_COMPRESSION") {
+            self.compression = compression;
+        }
+        if let Some(size) = env.parse::<u64>("CNOSDB_WAL_MAX_INFLIGHT")? {
+            self.max_inflight_bytes = size;
+        }
+        Ok(())
+    }
+
+    /// Compression of WAL segments, unknown names are rejected by `validate`
+    pub fn compression(&self) -> WalCompression {
+        WalCompression::new(&self.compression).unwrap_or_default()
+    }
+
+    /// Size of unsynced WAL data above which writes are blocked until fsync catches up
+    pub fn max_inflight_bytes(&self) -> u64 {
+        self.max_inflight_bytes
+    }
+
+    /// Directory of the WAL used by database `db`, shared by all databases unless `wal_per_database`
+    pub fn wal_path_for_db(&self, db: &str) -> PathBuf {
+        let path = PathBuf::from(&self.path);
+        if self.wal_per_database {
+            path.join(db)
+        } else {
+            path
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if WalCompression::new(&self.compression).is_none() {
+            return Err(ConfigError::new(
+                "wal.compression",
+                format!(
+                    "'{}' is not one of 'none', 'snappy', 'zstd'",
+                    self.compression
+                ),
+            ));
+        }
+        if self.max_inflight_bytes == 0 {
+            return Err(ConfigError::new(
+                "wal.max_inflight_bytes",
+                "must be greater than 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WalCompression {
+    #[default]
+    None,
+    Snappy,
+    Zstd,
+}
+
+impl WalCompression {
+    pub fn new(text: &str) -> Option<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "snappy" => Some(Self::Snappy),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
         }
     }
 }
@@ -92,100 +808,760 @@ impl WalConfig {
 pub struct CacheConfig {
     pub max_buffer_size: u64,
     pub max_immutable_number: u16,
+    #[serde(default = "CacheConfig::default_flush_watermark_pct")]
+    pub flush_watermark_pct: u8,
+    #[serde(default = "CacheConfig::default_immutable_memory_ratio")]
+    pub immutable_memory_ratio: f64,
 }
 
 impl CacheConfig {
-    pub fn override_by_env(&mut self) {
-        if let Ok(size) = std::env::var("CNOSDB_CACHE_MAX_BUFFER_SIZE") {
-            self.max_buffer_size = size.parse::<u64>().unwrap();
-        }
-        if let Ok(size) = std::env::var("CNOSDB_CACHE_MAX_IMMUTABLE_NUMBER") {
-            self.max_immutable_number = size.parse::<u16>().unwrap();
-        }
+    fn default_flush_watermark_pct() -> u8 {
+        75
     }
-}
 
-impl QueryConfig {
-    pub fn override_by_env(&mut self) {
-        if let Ok(size) = std::env::var("MAX_SERVER_CONNECTIONS") {
-            self.max_server_connections = size.parse::<u32>().unwrap();
+    fn default_immutable_memory_ratio() -> f64 {
+        0.5
+    }
+
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(size) = env.parse::<u64>("CNOSDB_CACHE_MAX_BUFFER_SIZE")? {
+            self.max_buffer_size = size;
         }
-        if let Ok(size) = std::env::var("QUERY_SQL_LIMIT") {
-            self.query_sql_limit = size.parse::<u64>().unwrap();
+        if let Some(size) = env.parse::<u16>("CNOSDB_CACHE_MAX_IMMUTABLE_NUMBER")? {
+            self.max_immutable_number = size;
         }
-        if let Ok(size) = std::env::var("WRITE_SQL_LIMIT") {
-            self.write_sql_limit = size.parse::<u64>().unwrap();
+        if let Some(pct) = env.parse::<u8>("CNOSDB_CACHE_FLUSH_WATERMARK_PCT")? {
+            self.flush_watermark_pct = pct;
         }
+        if let Some(ratio) = env.parse::<f64>("CNOSDB_CACHE_IMMUTABLE_MEMORY_RATIO")? {
+            self.immutable_memory_ratio = ratio;
+        }
+        Ok(())
     }
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LogConfig {
-    pub level: String,
-    pub path: String,
-}
+    /// Buffer size above which a background flush starts, before `max_buffer_size` is reached
+    pub fn flush_watermark_bytes(&self) -> u64 {
+        (self.max_buffer_size as u128 * self.flush_watermark_pct as u128 / 100) as u64
+    }
 
-impl LogConfig {
-    pub fn override_by_env(&mut self) {
-        if let Ok(level) = std::env::var("CNOSDB_LOG_LEVEL") {
-            self.level = level;
+    /// Part of `max_buffer_size` which immutable buffers waiting for flush may hold
+    pub fn immutable_budget_bytes(&self) -> u64 {
+        (self.max_buffer_size as f64 * self.immutable_memory_ratio) as u64
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.0..=1.0).contains(&self.immutable_memory_ratio) {
+            return Err(ConfigError::new(
+                "cache.immutable_memory_ratio",
+                format!("{} is not in range [0, 1]", self.immutable_memory_ratio),
+            ));
         }
-        if let Ok(path) = std::env::var("CNOSDB_LOG_PATH") {
-            self.path = path;
+        if !(1..=100).contains(&self.flush_watermark_pct) {
+            return Err(ConfigError::new(
+                "cache.flush_watermark_pct",
+                format!("{} is not in range [1, 100]", self.flush_watermark_pct),
+            ));
         }
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SecurityConfig {
-    pub tls_config: Option<TLSConfig>,
-}
+impl QueryConfig {
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(size) = env.parse::<u32>("MAX_SERVER_CONNECTIONS")? {
+            self.max_server_connections = size;
+        }
+        if let Some(size) = env.parse::<u64>("QUERY_SQL_LIMIT")? {
+            self.query_sql_limit = size;
+        }
+        if let Some(size) = env.parse::<u64>("WRITE_SQL_LIMIT")? {
+            self.write_sql_limit = size;
+        }
+        if let Some(size) = env.parse::<u64>("CNOSDB_MAX_WRITE_LINES")? {
+            self.max_write_lines = Some(size);
+        }
+        if let Some(partitions) = env.parse::<usize>("CNOSDB_TARGET_PARTITIONS")? {
+            self.target_partitions = Some(partitions);
+        }
+        if let Some(database) = env.get("CNOSDB_DEFAULT_DATABASE") {
+            self.default_database = database;
+        }
+        if let Some(size) = env.parse::<u64>("CNOSDB_MAX_RESULT_BYTES")? {
+            self.max_result_bytes = Some(size);
+        }
+        if let Some(insensitive) = env.parse::<bool>("CNOSDB_CASE_INSENSITIVE_IDENTIFIERS")? {
+            self.case_insensitive_identifiers = insensitive;
+        }
+        if let Some(depth) = env.parse::<u32>("CNOSDB_MAX_EXPR_DEPTH")? {
+            self.max_expr_depth = depth;
+        }
+        if let Some(timeout) = env.parse::<u64>("CNOSDB_QUERY_TIMEOUT_MS")? {
+            self.query_timeout_ms = Some(timeout);
+        }
+        if let Some(partial) = env.parse::<bool>("CNOSDB_PARTIAL_RESULTS_ON_TIMEOUT")? {
+            self.partial_results_on_timeout = partial;
+        }
+        if let Some(size) = env.parse::<u32>("CNOSDB_MAX_CONN_PER_DB")? {
+            self.max_connections_per_db = Some(size);
+        }
+        if let Some(timezone) = env.get("CNOSDB_TIMEZONE") {
+            self.default_timezone = timezone;
+        }
+        if let Some(consistency) = env.get("CNOSDB_WRITE_CONSISTENCY") {
+            self.write_consistency = consistency;
+        }
+        if let Some(statements) = env.get("CNOSDB_ALLOWED_STATEMENTS") {
+            self.allowed_statements = Some(
+                statements
+                    .split(',')
+                    .map(|kind| kind.trim().to_string())
+                    .filter(|kind| !kind.is_empty())
+                    .collect(),
+            );
+        }
+        Ok(())
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TLSConfig {
-    pub certificate: String,
-    pub private_key: String,
-}
+    fn default_database_name() -> String {
+        "public".to_string()
+    }
 
-pub fn get_config(path: &str) -> Config {
-    let mut file = match File::open(path) {
-        Ok(file) => file,
-        Err(err) => panic!("Failed to open configurtion file '{}': {}", path, err),
-    };
-    let mut content = String::new();
-    if let Err(err) = file.read_to_string(&mut content) {
-        panic!("Failed to read configurtion file '{}': {}", path, err);
+    fn default_max_expr_depth() -> u32 {
+        128
     }
-    let config: Config = match toml::from_str(&content) {
-        Ok(config) => config,
-        Err(err) => panic!("Failed to parse configurtion file '{}': {}", path, err),
-    };
-    info!("Start with configuration: {:#?}", config);
-    config
-}
 
-#[test]
-fn test() {
-    let config_str = r#"
-[query]
-max_server_connections = 10240
-query_sql_limit = 16777216   # 16 * 1024 * 1024
-write_sql_limit = 167772160   # 160 * 1024 * 1024
-[storage]
-path = 'data/db'
-max_summary_size = 134217728 # 128 * 1024 * 1024
-max_level = 4
-base_file_size = 16777216 # 16 * 1024 * 1024
-compact_trigger = 4
-max_compact_size = 2147483648 # 2 * 1024 * 1024 * 1024
-strict_write = true
+    fn default_timezone_name() -> String {
+        "UTC".to_string()
+    }
 
-[wal]
-enabled = true
-path = 'data/wal'
-sync = true
+    fn default_write_consistency() -> String {
+        "one".to_string()
+    }
 
-[cache]
+    /// Maximum number of lines accepted in a single line protocol write, unlimited if None
+    pub fn max_write_lines(&self) -> Option<u64> {
+        self.max_write_lines
+    }
+
+    /// Number of partitions for query execution, the number of CPUs if not set
+    pub fn target_partitions(&self) -> usize {
+        self.target_partitions.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+    }
+
+    /// Database used by queries which don't specify one
+    pub fn default_database(&self) -> &str {
+        &self.default_database
+    }
+
+    /// Maximum size of a serialized query result, unlimited if None
+    pub fn max_result_bytes(&self) -> Option<u64> {
+        self.max_result_bytes
+    }
+
+    /// IANA time zone used by queries for time functions like `date_trunc`
+    pub fn default_timezone(&self) -> &str {
+        &self.default_timezone
+    }
+
+    /// Number of replicas which must acknowledge a write, unknown names are rejected
+    /// by `validate`
+    pub fn write_consistency(&self) -> Consistency {
+        Consistency::new(&self.write_consistency).unwrap_or_default()
+    }
+
+    /// Maximum nesting depth of an expression in a SQL statement
+    pub fn max_expr_depth(&self) -> u32 {
+        self.max_expr_depth
+    }
+
+    /// Maximum execution time of a query, unlimited if None
+    pub fn query_timeout(&self) -> Option<std::time::Duration> {
+        self.query_timeout_ms.map(std::time::Duration::from_millis)
+    }
+
+    /// Whether a timed-out query returns the batches completed so far, flagged as
+    /// truncated, instead of an error
+    pub fn partial_results_on_timeout(&self) -> bool {
+        self.partial_results_on_timeout
+    }
+
+    /// Maximum number of concurrent connections to a single database, unlimited if None
+    pub fn max_connections_per_db(&self) -> Option<u32> {
+        self.max_connections_per_db
+    }
+
+    /// Whether a new connection to a database may be accepted, given the number of open
+    /// connections to the server and to that database
+    pub fn accepts_connection(&self, server_connections: u32, db_connections: u32) -> bool {
+        server_connections < self.max_server_connections
+            && self
+                .max_connections_per_db
+                .map_or(true, |limit| db_connections < limit)
+    }
+
+    /// Whether statements of `kind`, like "select" or "drop", may be executed.
+    /// Every kind is allowed if `allowed_statements` is not set.
+    pub fn is_statement_allowed(&self, kind: &str) -> bool {
+        match &self.allowed_statements {
+            Some(allowed) => allowed.iter().any(|a| a.eq_ignore_ascii_case(kind)),
+            None => true,
+        }
+    }
+
+    /// Whether database and table names are matched ignoring ASCII case
+    pub fn case_insensitive_identifiers(&self) -> bool {
+        self.case_insensitive_identifiers
+    }
+
+    /// Whether the identifier `name` refers to the object registered as `registered`
+    pub fn identifiers_match(&self, name: &str, registered: &str) -> bool {
+        if self.case_insensitive_identifiers {
+            name.eq_ignore_ascii_case(registered)
+        } else {
+            name == registered
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.default_database.is_empty() {
+            return Err(ConfigError::new(
+                "query.default_database",
+                "must not be empty",
+            ));
+        }
+        if self.max_result_bytes == Some(0) {
+            return Err(ConfigError::new(
+                "query.max_result_bytes",
+                "must be greater than 0",
+            ));
+        }
+        if self.target_partitions == Some(0) {
+            return Err(ConfigError::new(
+                "query.target_partitions",
+                "must be at least 1",
+            ));
+        }
+        if self.max_connections_per_db == Some(0) {
+            return Err(ConfigError::new(
+                "query.max_connections_per_db",
+                "must be greater than 0",
+            ));
+        }
+        if self.query_timeout_ms == Some(0) {
+            return Err(ConfigError::new(
+                "query.query_timeout_ms",
+                "must be greater than 0",
+            ));
+        }
+        if self.max_expr_depth < 1 {
+            return Err(ConfigError::new(
+                "query.max_expr_depth",
+                "must be at least 1",
+            ));
+        }
+        if self.default_timezone.parse::<chrono_tz::Tz>().is_err() {
+            return Err(ConfigError::new(
+                "query.default_timezone",
+                format!("'{}' is not a known IANA time zone", self.default_timezone),
+            ));
+        }
+        if Consistency::new(&self.write_consistency).is_none() {
+            return Err(ConfigError::new(
+                "query.write_consistency",
+                format!(
+                    "'{}' is not one of 'one', 'quorum', 'all'",
+                    self.write_consistency
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Consistency {
+    /// Acknowledged by any replica
+    #[default]
+    One,
+    /// Acknowledged by a majority of the replicas
+    Quorum,
+    /// Acknowledged by every replica
+    All,
+}
+
+impl Consistency {
+    pub fn new(text: &str) -> Option<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "one" => Some(Self::One),
+            "quorum" => Some(Self::Quorum),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogConfig {
+    pub level: String,
+    pub path: String,
+    #[serde(default)]
+    pub query_tracing: bool,
+}
+
+impl LogConfig {
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(level) = env.get("CNOSDB_LOG_LEVEL") {
+            self.level = level;
+        }
+        if let Some(path) = env.get("CNOSDB_LOG_PATH") {
+            self.path = path;
+        }
+        if let Some(tracing) = env.parse::<bool>("CNOSDB_QUERY_TRACING")? {
+            self.query_tracing = tracing;
+        }
+        Ok(())
+    }
+
+    /// Whether queries emit spans around the parse, plan and execute phases
+    pub fn query_tracing(&self) -> bool {
+        self.query_tracing
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    pub tls_config: Option<TLSConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TLSConfig {
+    pub certificate: String,
+    pub private_key: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WriteConfig {
+    pub max_writes_per_sec: Option<u64>,
+}
+
+impl WriteConfig {
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(size) = env.parse::<u64>("CNOSDB_MAX_WRITES_PER_SEC")? {
+            self.max_writes_per_sec = Some(size);
+        }
+        Ok(())
+    }
+
+    /// Maximum number of write requests admitted per second, unlimited if None
+    pub fn max_writes_per_sec(&self) -> Option<u64> {
+        self.max_writes_per_sec
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_writes_per_sec == Some(0) {
+            return Err(ConfigError::new(
+                "write.max_writes_per_sec",
+                "must be greater than 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompatConfig {
+    #[serde(default)]
+    pub influx_compat: bool,
+}
+
+impl CompatConfig {
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(compat) = env.parse::<bool>("CNOSDB_INFLUX_COMPAT")? {
+            self.influx_compat = compat;
+        }
+        Ok(())
+    }
+
+    /// Whether line protocol values are typed by InfluxDB's implicit rules, like unquoted
+    /// numbers being floats, passed to `ColumnType::from_line_protocol`
+    pub fn influx_compat(&self) -> bool {
+        self.influx_compat
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub destination: String,
+    #[serde(default = "BackupConfig::default_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "BackupConfig::default_retain")]
+    pub retain: u32,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            destination: String::new(),
+            interval_secs: Self::default_interval_secs(),
+            retain: Self::default_retain(),
+        }
+    }
+}
+
+impl BackupConfig {
+    fn default_interval_secs() -> u64 {
+        24 * 60 * 60
+    }
+
+    fn default_retain() -> u32 {
+        7
+    }
+
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(enabled) = env.parse::<bool>("CNOSDB_BACKUP_ENABLED")? {
+            self.enabled = enabled;
+        }
+        if let Some(destination) = env.get("CNOSDB_BACKUP_DESTINATION") {
+            self.destination = destination;
+        }
+        if let Some(secs) = env.parse::<u64>("CNOSDB_BACKUP_INTERVAL_SECS")? {
+            self.interval_secs = secs;
+        }
+        if let Some(retain) = env.parse::<u32>("CNOSDB_BACKUP_RETAIN")? {
+            self.retain = retain;
+        }
+        Ok(())
+    }
+
+    /// Directory or object store URL the snapshots are written to
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Time between two scheduled snapshots
+    pub fn interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.interval_secs)
+    }
+
+    /// Number of snapshots kept, older ones are removed
+    pub fn retain(&self) -> u32 {
+        self.retain
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.destination.is_empty() {
+            return Err(ConfigError::new(
+                "backup.destination",
+                "must not be empty when backup is enabled",
+            ));
+        }
+        if self.interval_secs == 0 {
+            return Err(ConfigError::new(
+                "backup.interval_secs",
+                "must be greater than 0",
+            ));
+        }
+        if self.retain < 1 {
+            return Err(ConfigError::new("backup.retain", "must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    pub total_memory_budget: Option<u64>,
+    #[serde(default = "MemoryConfig::default_query_memory_ratio")]
+    pub query_memory_ratio: f64,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            total_memory_budget: None,
+            query_memory_ratio: Self::default_query_memory_ratio(),
+        }
+    }
+}
+
+impl MemoryConfig {
+    fn default_query_memory_ratio() -> f64 {
+        0.5
+    }
+
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(size) = env.parse::<u64>("CNOSDB_TOTAL_MEMORY")? {
+            self.total_memory_budget = Some(size);
+        }
+        Ok(())
+    }
+
+    /// Part of the total memory budget for queries, unlimited if no budget is set
+    pub fn query_budget(&self) -> Option<u64> {
+        self.total_memory_budget
+            .map(|total| (total as f64 * self.query_memory_ratio) as u64)
+    }
+
+    /// Rest of the total memory budget for the cache, unlimited if no budget is set
+    pub fn cache_budget(&self) -> Option<u64> {
+        self.total_memory_budget
+            .zip(self.query_budget())
+            .map(|(total, query)| total - query)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.total_memory_budget == Some(0) {
+            return Err(ConfigError::new(
+                "memory.total_memory_budget",
+                "must be greater than 0",
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.query_memory_ratio) {
+            return Err(ConfigError::new(
+                "memory.query_memory_ratio",
+                format!("{} is not in range [0, 1]", self.query_memory_ratio),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Aggregate functions a downsample rule may use
+pub const DOWNSAMPLE_AGGREGATIONS: [&str; 7] =
+    ["count", "sum", "mean", "min", "max", "first", "last"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownsampleRule {
+    pub source_table: String,
+    pub every: String,
+    pub into_table: String,
+    pub aggregations: Vec<String>,
+}
+
+impl DownsampleRule {
+    /// Interval of the rollup, None if `every` is not a valid duration
+    pub fn every(&self) -> Option<Duration> {
+        Duration::new(&self.every)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.every().is_none() {
+            return Err(ConfigError::new(
+                "downsample.every",
+                format!(
+                    "'{}' of table '{}' is not a valid duration",
+                    self.every, self.source_table
+                ),
+            ));
+        }
+        if let Some(aggregation) = self
+            .aggregations
+            .iter()
+            .find(|agg| !DOWNSAMPLE_AGGREGATIONS.contains(&agg.to_lowercase().as_str()))
+        {
+            return Err(ConfigError::new(
+                "downsample.aggregations",
+                format!(
+                    "unknown aggregation '{}' of table '{}'",
+                    aggregation, self.source_table
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Storage parameters of a single table, unset ones follow the `[storage]` section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableOverride {
+    pub db: String,
+    pub table: String,
+    pub compact_trigger: Option<u32>,
+    pub base_file_size: Option<u64>,
+    pub codec_default: Option<u8>,
+}
+
+impl TableOverride {
+    pub fn codec_default(&self) -> Option<Encoding> {
+        self.codec_default.map(Encoding::from)
+    }
+}
+
+/// Permission of a client on a database, `client` and `database` may be `*` to match any
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessRule {
+    pub client: String,
+    pub database: String,
+    pub permission: String,
+}
+
+impl AccessRule {
+    const WILDCARD: &'static str = "*";
+
+    fn matches(&self, client: &str, db: &str) -> bool {
+        (self.client == Self::WILDCARD || self.client == client)
+            && (self.database == Self::WILDCARD || self.database == db)
+    }
+
+    /// A rule naming the client is more specific than one naming the database
+    fn specificity(&self) -> u8 {
+        let client = (self.client != Self::WILDCARD) as u8;
+        let database = (self.database != Self::WILDCARD) as u8;
+        client * 2 + database
+    }
+
+    /// Permission of the rule, unknown names are rejected by `validate`
+    pub fn permission(&self) -> Permission {
+        Permission::new(&self.permission).unwrap_or(Permission::Deny)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if Permission::new(&self.permission).is_none() {
+            return Err(ConfigError::new(
+                "access_rule.permission",
+                format!(
+                    "'{}' of client '{}' is not one of 'read', 'write', 'all', 'deny'",
+                    self.permission, self.client
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Read,
+    Write,
+    All,
+    Deny,
+}
+
+impl Permission {
+    pub fn new(text: &str) -> Option<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "read" => Some(Self::Read),
+            "write" => Some(Self::Write),
+            "all" => Some(Self::All),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+
+    pub fn can_read(&self) -> bool {
+        matches!(self, Self::Read | Self::All)
+    }
+
+    pub fn can_write(&self) -> bool {
+        matches!(self, Self::Write | Self::All)
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct AdminConfig {
+    pub addr: Option<String>,
+    #[serde(skip_serializing)]
+    pub auth_token: Option<String>,
+}
+
+impl AdminConfig {
+    pub fn override_by_env(&mut self, env: &EnvOverrides) -> Result<(), ConfigError> {
+        if let Some(token) = env.get("CNOSDB_ADMIN_TOKEN") {
+            self.auth_token = Some(token);
+        }
+        Ok(())
+    }
+
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        self.addr.as_ref().and_then(|addr| addr.parse().ok())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(addr) = &self.addr {
+            if addr.parse::<SocketAddr>().is_err() {
+                return Err(ConfigError::new(
+                    "admin.addr",
+                    format!("'{}' is not a valid socket address", addr),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// The auth token must not leak into logs when the configuration is printed,
+// it is also skipped when the configuration is serialized
+impl fmt::Debug for AdminConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminConfig")
+            .field("addr", &self.addr)
+            .field("auth_token", &self.auth_token.as_ref().map(|_| "******"))
+            .finish()
+    }
+}
+
+/// Read and validate the configuration file, without environment overrides
+pub fn get_config(path: &str) -> Result<Config, ConfigError> {
+    get_config_with_env(path, &EnvOverrides::default())
+}
+
+/// Read the configuration file, apply the overrides of `env` and validate the result
+pub fn get_config_with_env(path: &str, env: &EnvOverrides) -> Result<Config, ConfigError> {
+    let mut file = File::open(path)
+        .map_err(|err| ConfigError::new("", format!("failed to open file '{}': {}", path, err)))?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .map_err(|err| ConfigError::new("", format!("failed to read file '{}': {}", path, err)))?;
+    let mut config: Config = toml::from_str(&content)
+        .map_err(|err| ConfigError::new("", format!("failed to parse file '{}': {}", path, err)))?;
+    config.override_by_env(env)?;
+    config.validate()?;
+    info!("Start with configuration: {:#?}", config);
+    Ok(config)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveTime;
+    use models::codec::Encoding;
+    use models::schema::{Duration, Precision, PrecisionRounding, TableColumn, TskvTableSchema};
+
+    use crate::{
+        get_config, get_config_with_env, validate_file_json, Config, Consistency, EnvOverrides,
+        FlushClock, Permission, StorageEngine, WalCompression,
+    };
+
+    const TEST_CONFIG: &str = r#"
+[query]
+max_server_connections = 10240
+query_sql_limit = 16777216   # 16 * 1024 * 1024
+write_sql_limit = 167772160   # 160 * 1024 * 1024
+[storage]
+path = 'data/db'
+max_summary_size = 134217728 # 128 * 1024 * 1024
+max_level = 4
+base_file_size = 16777216 # 16 * 1024 * 1024
+compact_trigger = 4
+max_compact_size = 2147483648 # 2 * 1024 * 1024 * 1024
+strict_write = true
+
+[wal]
+enabled = true
+path = 'data/wal'
+sync = true
+
+[cache]
 max_buffer_size = 1048576 # 134217728 # 128 * 1024 * 1024
 max_immutable_number = 4
 
@@ -197,6 +1573,1189 @@ path = 'data/log'
 
 "#;
 
-    let config: Config = toml::from_str(config_str).unwrap();
-    dbg!(config);
+    fn env(vars: &[(&str, &str)]) -> EnvOverrides {
+        EnvOverrides::new(
+            vars.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    fn test_config() -> Config {
+        toml::from_str(TEST_CONFIG).unwrap()
+    }
+
+    /// Parse the test configuration with extra `lines` appended to the `[section]` table.
+    fn test_config_with(section: &str, lines: &str) -> Config {
+        let header = format!("[{}]\n", section);
+        let config_str = TEST_CONFIG.replacen(&header, &format!("{}{}\n", header, lines), 1);
+        toml::from_str(&config_str).unwrap()
+    }
+
+    #[test]
+    fn test() {
+        let config = test_config();
+        dbg!(config);
+    }
+
+    #[test]
+    fn test_max_write_lines() {
+        let config = test_config();
+        assert_eq!(config.query.max_write_lines(), None);
+
+        let config = test_config_with("query", "max_write_lines = 1000");
+        assert_eq!(config.query.max_write_lines(), Some(1000));
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_MAX_WRITE_LINES", "500")]))
+            .unwrap();
+        assert_eq!(config.query.max_write_lines(), Some(500));
+    }
+
+    #[test]
+    fn test_read_only_window() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+
+        let config = test_config_with("storage", "read_only_window = '01:00-03:30'");
+        config.validate().unwrap();
+        assert!(config.storage.is_read_only_at(at(1, 0)));
+        assert!(config.storage.is_read_only_at(at(2, 15)));
+        assert!(!config.storage.is_read_only_at(at(3, 30)));
+        assert!(!config.storage.is_read_only_at(at(12, 0)));
+
+        let config = test_config_with("storage", "read_only_window = '23:00-01:00'");
+        assert!(config.storage.is_read_only_at(at(23, 30)));
+        assert!(config.storage.is_read_only_at(at(0, 30)));
+        assert!(!config.storage.is_read_only_at(at(1, 30)));
+
+        let config = test_config_with("storage", "read_only = true");
+        assert!(config.storage.is_read_only_at(at(12, 0)));
+        let config = test_config();
+        assert!(!config.storage.is_read_only_at(at(12, 0)));
+
+        let config = test_config_with("storage", "read_only_window = '1am-3am'");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_wal_per_database() {
+        let config = test_config();
+        assert!(!config.wal.wal_per_database);
+        assert_eq!(
+            config.wal.wal_path_for_db("db1"),
+            std::path::PathBuf::from("data/wal")
+        );
+
+        let config = test_config_with("wal", "wal_per_database = true");
+        assert!(config.wal.wal_per_database);
+        assert_eq!(
+            config.wal.wal_path_for_db("db1"),
+            std::path::PathBuf::from("data/wal/db1")
+        );
+
+        let mut config = test_config();
+        config
+            .wal
+            .override_by_env(&env(&[("CNOSDB_WAL_PER_DATABASE", "true")]))
+            .unwrap();
+        assert!(config.wal.wal_per_database);
+
+        let mut config = test_config();
+        assert!(config
+            .wal
+            .override_by_env(&env(&[("CNOSDB_WAL_PER_DATABASE", "True")]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_wal_bool_env_overrides() {
+        let mut config = test_config();
+        config
+            .wal
+            .override_by_env(&env(&[
+                ("CNOSDB_WAL_ENABLED", "false"),
+                ("CNOSDB_WAL_SYNC", "false"),
+            ]))
+            .unwrap();
+        assert!(!config.wal.enabled);
+        assert!(!config.wal.sync);
+
+        config
+            .wal
+            .override_by_env(&env(&[
+                ("CNOSDB_WAL_ENABLED", "true"),
+                ("CNOSDB_WAL_SYNC", "true"),
+            ]))
+            .unwrap();
+        assert!(config.wal.enabled);
+        assert!(config.wal.sync);
+
+        for key in ["CNOSDB_WAL_ENABLED", "CNOSDB_WAL_SYNC"] {
+            let mut config = test_config();
+            assert!(config.wal.override_by_env(&env(&[(key, "1")])).is_err());
+        }
+    }
+
+    #[test]
+    fn test_time_codec_default() {
+        let config = test_config();
+        assert_eq!(config.storage.time_codec_default(), Encoding::Default);
+
+        let config = test_config_with("storage", "time_codec_default = 2");
+        config.validate().unwrap();
+        assert_eq!(config.storage.time_codec_default(), Encoding::Delta);
+
+        let mut time_column = TableColumn::new_time_column(0);
+        time_column.encoding = config.storage.time_codec_default();
+        let schema = TskvTableSchema::new("public".to_string(), "t".to_string(), vec![time_column]);
+        assert_eq!(schema.time_codec(), Some(Encoding::Delta));
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[("CNOSDB_STORAGE_TIME_CODEC_DEFAULT", "3")]))
+            .unwrap();
+        assert_eq!(config.storage.time_codec_default(), Encoding::Quantile);
+
+        let config = test_config_with("storage", "time_codec_default = 6");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_admin_config() {
+        let config = test_config();
+        assert!(config.admin.addr.is_none());
+        assert!(config.admin.auth_token.is_none());
+
+        let config_str = format!(
+            "{}\n[admin]\naddr = '127.0.0.1:8903'\nauth_token = 'secret_token'\n",
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.admin.socket_addr(),
+            Some("127.0.0.1:8903".parse().unwrap())
+        );
+        assert_eq!(config.admin.auth_token.as_deref(), Some("secret_token"));
+        let dump = format!("{:?}", config);
+        assert!(!dump.contains("secret_token"));
+        assert!(dump.contains("******"));
+        let serialized = serde_json::to_string(&config).unwrap();
+        assert!(!serialized.contains("secret_token"));
+        assert!(serialized.contains("127.0.0.1:8903"));
+
+        let mut config = test_config();
+        config
+            .admin
+            .override_by_env(&env(&[("CNOSDB_ADMIN_TOKEN", "env_token")]))
+            .unwrap();
+        assert_eq!(config.admin.auth_token.as_deref(), Some("env_token"));
+
+        let config_str = format!("{}\n[admin]\naddr = 'localhost'\n", TEST_CONFIG);
+        let config: Config = toml::from_str(&config_str).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_background_threads() {
+        let config = test_config();
+        let cpus = std::thread::available_parallelism().unwrap().get();
+        assert_eq!(config.storage.background_threads(), (cpus / 2).max(1));
+
+        let config = test_config_with("storage", "background_threads = 3");
+        config.validate().unwrap();
+        assert_eq!(config.storage.background_threads(), 3);
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[("CNOSDB_BACKGROUND_THREADS", "5")]))
+            .unwrap();
+        assert_eq!(config.storage.background_threads(), 5);
+
+        let config = test_config_with("storage", "background_threads = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_target_partitions() {
+        let config = test_config();
+        let cpus = std::thread::available_parallelism().unwrap().get();
+        assert_eq!(config.query.target_partitions(), cpus);
+
+        let config = test_config_with("query", "target_partitions = 8");
+        config.validate().unwrap();
+        assert_eq!(config.query.target_partitions(), 8);
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_TARGET_PARTITIONS", "16")]))
+            .unwrap();
+        assert_eq!(config.query.target_partitions(), 16);
+
+        let config = test_config_with("query", "target_partitions = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_verify_checksums_on_read() {
+        let config = test_config();
+        assert!(!config.storage.verify_checksums_on_read());
+
+        let config = test_config_with("storage", "verify_checksums_on_read = true");
+        assert!(config.storage.verify_checksums_on_read());
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[("CNOSDB_VERIFY_CHECKSUMS", "true")]))
+            .unwrap();
+        assert!(config.storage.verify_checksums_on_read());
+    }
+
+    #[test]
+    fn test_max_writes_per_sec() {
+        let config = test_config();
+        assert_eq!(config.write.max_writes_per_sec(), None);
+
+        let config_str = format!("{}\n[write]\nmax_writes_per_sec = 1000\n", TEST_CONFIG);
+        let config: Config = toml::from_str(&config_str).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.write.max_writes_per_sec(), Some(1000));
+
+        let mut config = test_config();
+        config
+            .write
+            .override_by_env(&env(&[("CNOSDB_MAX_WRITES_PER_SEC", "200")]))
+            .unwrap();
+        assert_eq!(config.write.max_writes_per_sec(), Some(200));
+
+        let config_str = format!("{}\n[write]\nmax_writes_per_sec = 0\n", TEST_CONFIG);
+        let config: Config = toml::from_str(&config_str).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_default_database() {
+        let config = test_config();
+        assert_eq!(config.query.default_database(), "public");
+
+        let config = test_config_with("query", "default_database = 'db1'");
+        config.validate().unwrap();
+        assert_eq!(config.query.default_database(), "db1");
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_DEFAULT_DATABASE", "db2")]))
+            .unwrap();
+        assert_eq!(config.query.default_database(), "db2");
+
+        let config = test_config_with("query", "default_database = ''");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_max_fields_per_table() {
+        let config = test_config();
+        assert_eq!(config.storage.max_fields_per_table, None);
+
+        let config = test_config_with("storage", "max_fields_per_table = 100");
+        config.validate().unwrap();
+        assert_eq!(config.storage.max_fields_per_table, Some(100));
+
+        let config = test_config_with("storage", "max_fields_per_table = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_file_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let config_str = TEST_CONFIG
+            .replace("[storage]\n", "[storage]\nbackground_threads = 0\n")
+            .replace("enabled = true", "enabled = false");
+        std::fs::write(&path, config_str).unwrap();
+
+        let json = validate_file_json(path.to_str().unwrap()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["ok"], serde_json::Value::Bool(false));
+        let errors = value["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["key"], "storage.background_threads");
+        let warnings = value["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0]["key"], "wal.enabled");
+
+        std::fs::write(&path, TEST_CONFIG).unwrap();
+        let json = validate_file_json(path.to_str().unwrap()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["ok"], serde_json::Value::Bool(true));
+        assert!(value["errors"].as_array().unwrap().is_empty());
+
+        std::fs::write(&path, "[storage\n").unwrap();
+        let json = validate_file_json(path.to_str().unwrap()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["ok"], serde_json::Value::Bool(false));
+        assert_eq!(value["errors"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_max_result_bytes() {
+        let config = test_config();
+        assert_eq!(config.query.max_result_bytes(), None);
+
+        let config = test_config_with("query", "max_result_bytes = 1048576");
+        config.validate().unwrap();
+        assert_eq!(config.query.max_result_bytes(), Some(1048576));
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_MAX_RESULT_BYTES", "4096")]))
+            .unwrap();
+        assert_eq!(config.query.max_result_bytes(), Some(4096));
+
+        let config = test_config_with("query", "max_result_bytes = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_downsample_rules() {
+        let config = test_config();
+        assert!(config.downsample.is_empty());
+
+        let config_str = format!(
+            r#"{}
+[[downsample]]
+source_table = 'cpu'
+every = '1h'
+into_table = 'cpu_1h'
+aggregations = ['mean', 'max']
+
+[[downsample]]
+source_table = 'mem'
+every = '1d'
+into_table = 'mem_1d'
+aggregations = ['last']
+"#,
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.downsample.len(), 2);
+        assert_eq!(config.downsample[0].source_table, "cpu");
+        assert_eq!(config.downsample[0].every(), Duration::new("1h"));
+        assert_eq!(config.downsample[0].aggregations, vec!["mean", "max"]);
+        assert_eq!(config.downsample[1].into_table, "mem_1d");
+
+        let config_str = format!(
+            r#"{}
+[[downsample]]
+source_table = 'cpu'
+every = 'hourly'
+into_table = 'cpu_1h'
+aggregations = ['mean']
+"#,
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        let err = config.validate().unwrap_err();
+        assert_eq!(err.key, "downsample.every");
+    }
+
+    #[test]
+    fn test_query_tracing() {
+        let config = test_config();
+        assert!(!config.log.query_tracing());
+
+        let config = test_config_with("log", "query_tracing = true");
+        assert!(config.log.query_tracing());
+
+        let mut config = test_config();
+        config
+            .log
+            .override_by_env(&env(&[("CNOSDB_QUERY_TRACING", "true")]))
+            .unwrap();
+        assert!(config.log.query_tracing());
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let config = test_config();
+        assert_eq!(config.memory.query_budget(), None);
+        assert_eq!(config.memory.cache_budget(), None);
+
+        let config_str = format!(
+            "{}\n[memory]\ntotal_memory_budget = 1000\nquery_memory_ratio = 0.3\n",
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.memory.query_budget(), Some(300));
+        assert_eq!(config.memory.cache_budget(), Some(700));
+
+        let mut config = test_config();
+        config
+            .memory
+            .override_by_env(&env(&[("CNOSDB_TOTAL_MEMORY", "2048")]))
+            .unwrap();
+        assert_eq!(config.memory.query_budget(), Some(1024));
+        assert_eq!(config.memory.cache_budget(), Some(1024));
+
+        for ratio in ["1.5", "-0.1"] {
+            let config_str = format!(
+                "{}\n[memory]\ntotal_memory_budget = 1000\nquery_memory_ratio = {}\n",
+                TEST_CONFIG, ratio
+            );
+            let config: Config = toml::from_str(&config_str).unwrap();
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_flush_clock() {
+        let config = test_config();
+        assert_eq!(config.storage.flush_clock(), FlushClock::Monotonic);
+
+        let config = test_config_with("storage", "flush_clock = 'wall'");
+        config.validate().unwrap();
+        assert_eq!(config.storage.flush_clock(), FlushClock::Wall);
+
+        let config = test_config_with("storage", "flush_clock = 'monotonic'");
+        config.validate().unwrap();
+        assert_eq!(config.storage.flush_clock(), FlushClock::Monotonic);
+
+        let config = test_config_with("storage", "flush_clock = 'tsc'");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_wal_compression() {
+        let config = test_config();
+        assert_eq!(config.wal.compression(), WalCompression::None);
+
+        for (text, compression) in [
+            ("none", WalCompression::None),
+            ("snappy", WalCompression::Snappy),
+            ("zstd", WalCompression::Zstd),
+        ] {
+            let config = test_config_with("wal", &format!("compression = '{}'", text));
+            config.validate().unwrap();
+            assert_eq!(config.wal.compression(), compression);
+        }
+
+        let config = test_config_with("wal", "compression = 'gzip'");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_table_override() {
+        let config = test_config();
+        assert!(config.table_override("public", "cpu").is_none());
+
+        let config_str = format!(
+            "{}
+[[table_override]]
+db = 'public'
+table = 'cpu'
+compact_trigger = 8
+codec_default = 2
+
+[[table_override]]
+db = 'public'
+table = 'mem'
+base_file_size = 1048576
+",
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        assert_eq!(config.table_override.len(), 2);
+
+        let cpu = config.table_override("public", "cpu").unwrap();
+        assert_eq!(cpu.compact_trigger, Some(8));
+        assert_eq!(cpu.base_file_size, None);
+        assert_eq!(cpu.codec_default(), Some(Encoding::Delta));
+
+        let mem = config.table_override("public", "mem").unwrap();
+        assert_eq!(mem.compact_trigger, None);
+        assert_eq!(mem.base_file_size, Some(1048576));
+
+        assert!(config.table_override("test", "cpu").is_none());
+    }
+
+    #[test]
+    fn test_case_insensitive_identifiers() {
+        let tables = ["cpu", "Mem"];
+        let table_exist = |config: &Config, name: &str| {
+            tables
+                .iter()
+                .any(|table| config.query.identifiers_match(name, table))
+        };
+
+        let config = test_config();
+        assert!(!config.query.case_insensitive_identifiers());
+        assert!(table_exist(&config, "cpu"));
+        assert!(table_exist(&config, "Mem"));
+        assert!(!table_exist(&config, "CPU"));
+        assert!(!table_exist(&config, "mem"));
+
+        let config = test_config_with("query", "case_insensitive_identifiers = true");
+        assert!(config.query.case_insensitive_identifiers());
+        assert!(table_exist(&config, "CPU"));
+        assert!(table_exist(&config, "mem"));
+        assert!(!table_exist(&config, "disk"));
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_CASE_INSENSITIVE_IDENTIFIERS", "true")]))
+            .unwrap();
+        assert!(config.query.case_insensitive_identifiers());
+    }
+
+    #[test]
+    fn test_preload_tables() {
+        let config = test_config();
+        assert!(config.storage.preload_tables().is_empty());
+
+        let config = test_config_with("storage", "preload_tables = ['public.cpu', 'test.mem']");
+        config.validate().unwrap();
+        assert_eq!(
+            config.storage.preload_tables(),
+            vec![
+                ("public".to_string(), "cpu".to_string()),
+                ("test".to_string(), "mem".to_string()),
+            ]
+        );
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[(
+                "CNOSDB_STORAGE_PRELOAD_TABLES",
+                "public.cpu, public.disk",
+            )]))
+            .unwrap();
+        assert_eq!(
+            config.storage.preload_tables(),
+            vec![
+                ("public".to_string(), "cpu".to_string()),
+                ("public".to_string(), "disk".to_string()),
+            ]
+        );
+
+        for entry in ["cpu", "public.cpu.usage", ".cpu"] {
+            let config = test_config_with("storage", &format!("preload_tables = ['{}']", entry));
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_wal_max_inflight_bytes() {
+        let config = test_config();
+        assert_eq!(config.wal.max_inflight_bytes(), 64 * 1024 * 1024);
+
+        let config = test_config_with("wal", "max_inflight_bytes = 1048576");
+        config.validate().unwrap();
+        assert_eq!(config.wal.max_inflight_bytes(), 1048576);
+
+        let mut config = test_config();
+        config
+            .wal
+            .override_by_env(&env(&[("CNOSDB_WAL_MAX_INFLIGHT", "4096")]))
+            .unwrap();
+        assert_eq!(config.wal.max_inflight_bytes(), 4096);
+
+        let config = test_config_with("wal", "max_inflight_bytes = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_storage_engine() {
+        let config = test_config();
+        assert_eq!(config.storage.engine(), StorageEngine::Tsm);
+
+        for (text, engine) in [
+            ("tsm", StorageEngine::Tsm),
+            ("parquet", StorageEngine::Parquet),
+        ] {
+            let config = test_config_with("storage", &format!("engine = '{}'", text));
+            config.validate().unwrap();
+            assert_eq!(config.storage.engine(), engine);
+        }
+
+        let config = test_config_with("storage", "engine = 'rocksdb'");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_max_expr_depth() {
+        let config = test_config();
+        assert_eq!(config.query.max_expr_depth(), 128);
+
+        let config = test_config_with("query", "max_expr_depth = 32");
+        config.validate().unwrap();
+        assert_eq!(config.query.max_expr_depth(), 32);
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_MAX_EXPR_DEPTH", "64")]))
+            .unwrap();
+        assert_eq!(config.query.max_expr_depth(), 64);
+
+        let config = test_config_with("query", "max_expr_depth = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_auto_index_tags() {
+        let config = test_config();
+        assert!(config.storage.auto_index_tags());
+
+        let config = test_config_with("storage", "auto_index_tags = false");
+        assert!(!config.storage.auto_index_tags());
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[("CNOSDB_AUTO_INDEX_TAGS", "false")]))
+            .unwrap();
+        assert!(!config.storage.auto_index_tags());
+    }
+
+    #[test]
+    fn test_precision_rounding() {
+        let config = test_config();
+        assert_eq!(
+            config.storage.precision_rounding(),
+            PrecisionRounding::Truncate
+        );
+
+        for (text, rounding, ms) in [
+            ("truncate", PrecisionRounding::Truncate, 1),
+            ("round", PrecisionRounding::Round, 2),
+            ("ceil", PrecisionRounding::Ceil, 2),
+        ] {
+            let config = test_config_with("storage", &format!("precision_rounding = '{}'", text));
+            config.validate().unwrap();
+            assert_eq!(config.storage.precision_rounding(), rounding);
+            assert_eq!(
+                Precision::MS.convert_nanos(1_500_000, config.storage.precision_rounding()),
+                ms
+            );
+        }
+
+        let config = test_config_with("storage", "precision_rounding = 'floor'");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_backup_config() {
+        let config = test_config();
+        assert!(!config.backup.enabled);
+        assert_eq!(
+            config.backup.interval(),
+            std::time::Duration::from_secs(86400)
+        );
+        assert_eq!(config.backup.retain(), 7);
+
+        let config_str = format!(
+            "{}\n[backup]\nenabled = true\ndestination = '/backup'\ninterval_secs = 3600\nretain = 3\n",
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.backup.destination(), "/backup");
+        assert_eq!(
+            config.backup.interval(),
+            std::time::Duration::from_secs(3600)
+        );
+        assert_eq!(config.backup.retain(), 3);
+
+        let mut config = test_config();
+        config
+            .backup
+            .override_by_env(&env(&[
+                ("CNOSDB_BACKUP_ENABLED", "true"),
+                ("CNOSDB_BACKUP_DESTINATION", "s3://bucket/cnosdb"),
+                ("CNOSDB_BACKUP_INTERVAL_SECS", "600"),
+                ("CNOSDB_BACKUP_RETAIN", "2"),
+            ]))
+            .unwrap();
+        assert!(config.backup.enabled);
+        assert_eq!(config.backup.destination(), "s3://bucket/cnosdb");
+        assert_eq!(
+            config.backup.interval(),
+            std::time::Duration::from_secs(600)
+        );
+        assert_eq!(config.backup.retain(), 2);
+
+        for lines in ["interval_secs = 0", "retain = 0"] {
+            let config_str = format!(
+                "{}\n[backup]\nenabled = true\ndestination = '/backup'\n{}\n",
+                TEST_CONFIG, lines
+            );
+            let config: Config = toml::from_str(&config_str).unwrap();
+            assert!(config.validate().is_err());
+
+            let config_str = format!("{}\n[backup]\n{}\n", TEST_CONFIG, lines);
+            let config: Config = toml::from_str(&config_str).unwrap();
+            config.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_partial_results_on_timeout() {
+        let config = test_config();
+        assert_eq!(config.query.query_timeout(), None);
+        assert!(!config.query.partial_results_on_timeout());
+
+        let config = test_config_with(
+            "query",
+            "query_timeout_ms = 30000\npartial_results_on_timeout = true",
+        );
+        assert_eq!(
+            config.query.query_timeout(),
+            Some(std::time::Duration::from_secs(30))
+        );
+        assert!(config.query.partial_results_on_timeout());
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[
+                ("CNOSDB_QUERY_TIMEOUT_MS", "500"),
+                ("CNOSDB_PARTIAL_RESULTS_ON_TIMEOUT", "true"),
+            ]))
+            .unwrap();
+        assert_eq!(
+            config.query.query_timeout(),
+            Some(std::time::Duration::from_millis(500))
+        );
+        assert!(config.query.partial_results_on_timeout());
+
+        let config = test_config_with("query", "query_timeout_ms = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_allowed_statements() {
+        let config = test_config();
+        for kind in ["select", "insert", "create", "drop"] {
+            assert!(config.query.is_statement_allowed(kind));
+        }
+
+        let config = test_config_with("query", "allowed_statements = ['select']");
+        assert!(config.query.is_statement_allowed("select"));
+        assert!(config.query.is_statement_allowed("SELECT"));
+        assert!(!config.query.is_statement_allowed("drop"));
+        assert!(!config.query.is_statement_allowed("insert"));
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_ALLOWED_STATEMENTS", "select, insert")]))
+            .unwrap();
+        assert!(config.query.is_statement_allowed("insert"));
+        assert!(!config.query.is_statement_allowed("drop"));
+    }
+
+    #[test]
+    fn test_flush_watermark() {
+        let config = test_config();
+        assert_eq!(config.cache.flush_watermark_pct, 75);
+        assert_eq!(
+            config.cache.flush_watermark_bytes(),
+            config.cache.max_buffer_size * 3 / 4
+        );
+
+        let config = test_config_with("cache", "flush_watermark_pct = 50");
+        config.validate().unwrap();
+        assert_eq!(
+            config.cache.flush_watermark_bytes(),
+            config.cache.max_buffer_size / 2
+        );
+
+        let mut config = test_config();
+        config.cache.max_buffer_size = 1001;
+        config
+            .cache
+            .override_by_env(&env(&[("CNOSDB_CACHE_FLUSH_WATERMARK_PCT", "100")]))
+            .unwrap();
+        assert_eq!(config.cache.flush_watermark_bytes(), 1001);
+
+        for pct in [0, 101] {
+            let config = test_config_with("cache", &format!("flush_watermark_pct = {}", pct));
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_access_rule() {
+        let config = test_config();
+        assert_eq!(
+            config.effective_permission("grafana", "public"),
+            Permission::All
+        );
+
+        let config_str = format!(
+            "{}
+[[access_rule]]
+client = '*'
+database = 'metrics'
+permission = 'read'
+
+[[access_rule]]
+client = 'ingest'
+database = 'metrics'
+permission = 'all'
+
+[[access_rule]]
+client = 'guest'
+database = '*'
+permission = 'deny'
+",
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        config.validate().unwrap();
+
+        let permission = config.effective_permission("grafana", "metrics");
+        assert_eq!(permission, Permission::Read);
+        assert!(permission.can_read());
+        assert!(!permission.can_write());
+
+        assert_eq!(
+            config.effective_permission("ingest", "metrics"),
+            Permission::All
+        );
+
+        let permission = config.effective_permission("guest", "metrics");
+        assert_eq!(permission, Permission::Deny);
+        assert!(!permission.can_read());
+
+        assert_eq!(
+            config.effective_permission("grafana", "public"),
+            Permission::All
+        );
+
+        let config_str = format!(
+            "{}\n[[access_rule]]\nclient = '*'\ndatabase = '*'\npermission = 'admin'\n",
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_auto_prune_empty_db() {
+        let config = test_config();
+        assert!(!config.storage.auto_prune_empty_db());
+        assert_eq!(
+            config.storage.empty_db_grace(),
+            std::time::Duration::from_secs(86400)
+        );
+
+        let config = test_config_with(
+            "storage",
+            "auto_prune_empty_db = true\nempty_db_grace_secs = 3600",
+        );
+        config.validate().unwrap();
+        assert!(config.storage.auto_prune_empty_db());
+        assert_eq!(
+            config.storage.empty_db_grace(),
+            std::time::Duration::from_secs(3600)
+        );
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[
+                ("CNOSDB_STORAGE_AUTO_PRUNE_EMPTY_DB", "true"),
+                ("CNOSDB_STORAGE_EMPTY_DB_GRACE_SECS", "600"),
+            ]))
+            .unwrap();
+        assert!(config.storage.auto_prune_empty_db());
+        assert_eq!(
+            config.storage.empty_db_grace(),
+            std::time::Duration::from_secs(600)
+        );
+
+        let config = test_config_with(
+            "storage",
+            "auto_prune_empty_db = true\nempty_db_grace_secs = 0",
+        );
+        assert!(config.validate().is_err());
+        let config = test_config_with("storage", "empty_db_grace_secs = 0");
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_max_connections_per_db() {
+        let config = test_config();
+        assert_eq!(config.query.max_connections_per_db(), None);
+        assert!(config.query.accepts_connection(100, 100));
+        assert!(!config
+            .query
+            .accepts_connection(config.query.max_server_connections, 0));
+
+        let config = test_config_with("query", "max_connections_per_db = 10");
+        config.validate().unwrap();
+        assert_eq!(config.query.max_connections_per_db(), Some(10));
+        assert!(config.query.accepts_connection(100, 9));
+        assert!(!config.query.accepts_connection(100, 10));
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_MAX_CONN_PER_DB", "20")]))
+            .unwrap();
+        assert_eq!(config.query.max_connections_per_db(), Some(20));
+
+        let config = test_config_with("query", "max_connections_per_db = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_startup_self_check() {
+        let config = test_config();
+        assert!(config.storage.startup_self_check());
+
+        let config = test_config_with("storage", "startup_self_check = false");
+        assert!(!config.storage.startup_self_check());
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[("CNOSDB_STORAGE_STARTUP_SELF_CHECK", "false")]))
+            .unwrap();
+        assert!(!config.storage.startup_self_check());
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config();
+        config.storage.path = dir.path().join("db").to_string_lossy().to_string();
+        config.wal.path = dir.path().join("wal").to_string_lossy().to_string();
+        config.run_self_check().unwrap();
+        assert!(dir.path().join("wal").is_dir());
+        assert_eq!(std::fs::read_dir(dir.path().join("db")).unwrap().count(), 0);
+
+        // A regular file in place of the directory can't be written into,
+        // unlike a read-only directory when the tests run as root.
+        let file = dir.path().join("read_only");
+        std::fs::write(&file, "").unwrap();
+        config.wal.path = file.to_string_lossy().to_string();
+        let err = config.run_self_check().unwrap_err();
+        assert_eq!(err.path, file);
+    }
+
+    #[test]
+    fn test_immutable_memory_ratio() {
+        let config = test_config();
+        assert_eq!(
+            config.cache.immutable_budget_bytes(),
+            config.cache.max_buffer_size / 2
+        );
+
+        let config = test_config_with("cache", "immutable_memory_ratio = 0.25");
+        config.validate().unwrap();
+        assert_eq!(
+            config.cache.immutable_budget_bytes(),
+            config.cache.max_buffer_size / 4
+        );
+
+        let mut config = test_config();
+        config
+            .cache
+            .override_by_env(&env(&[("CNOSDB_CACHE_IMMUTABLE_MEMORY_RATIO", "0")]))
+            .unwrap();
+        assert_eq!(config.cache.immutable_budget_bytes(), 0);
+
+        for ratio in ["1.5", "-0.5"] {
+            let config = test_config_with("cache", &format!("immutable_memory_ratio = {}", ratio));
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_default_timezone() {
+        let config = test_config();
+        config.validate().unwrap();
+        assert_eq!(config.query.default_timezone(), "UTC");
+
+        let config = test_config_with("query", "default_timezone = 'Asia/Shanghai'");
+        config.validate().unwrap();
+        assert_eq!(config.query.default_timezone(), "Asia/Shanghai");
+
+        let mut config = test_config();
+        config
+            .query
+            .override_by_env(&env(&[("CNOSDB_TIMEZONE", "Europe/Berlin")]))
+            .unwrap();
+        assert_eq!(config.query.default_timezone(), "Europe/Berlin");
+
+        let config = test_config_with("query", "default_timezone = 'Mars/Olympus_Mons'");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_deterministic_field_ids() {
+        let config = test_config();
+        assert!(!config.storage.deterministic_field_ids());
+
+        let config = test_config_with("storage", "deterministic_field_ids = true");
+        assert!(config.storage.deterministic_field_ids());
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[("CNOSDB_STORAGE_DETERMINISTIC_FIELD_IDS", "true")]))
+            .unwrap();
+        assert!(config.storage.deterministic_field_ids());
+    }
+
+    #[test]
+    fn test_max_tags_per_series() {
+        let config = test_config();
+        assert_eq!(config.storage.max_tags_per_series(), None);
+
+        let config = test_config_with("storage", "max_tags_per_series = 32");
+        config.validate().unwrap();
+        assert_eq!(config.storage.max_tags_per_series(), Some(32));
+
+        let mut config = test_config();
+        config
+            .storage
+            .override_by_env(&env(&[("CNOSDB_MAX_TAGS_PER_SERIES", "16")]))
+            .unwrap();
+        assert_eq!(config.storage.max_tags_per_series(), Some(16));
+
+        let config = test_config_with("storage", "max_tags_per_series = 0");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_write_consistency() {
+        let config = test_config();
+        assert_eq!(config.query.write_consistency(), Consistency::One);
+
+        for (text, consistency) in [
+            ("one", Consistency::One),
+            ("quorum", Consistency::Quorum),
+            ("all", Consistency::All),
+        ] {
+            let config = test_config_with("query", &format!("write_consistency = '{}'", text));
+            config.validate().unwrap();
+            assert_eq!(config.query.write_consistency(), consistency);
+        }
+
+        let config = test_config_with("query", "write_consistency = 'two'");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_influx_compat() {
+        let config = test_config();
+        assert!(!config.compat.influx_compat());
+
+        let config_str = format!("{}\n[compat]\ninflux_compat = true\n", TEST_CONFIG);
+        let config: Config = toml::from_str(&config_str).unwrap();
+        config.validate().unwrap();
+        assert!(config.compat.influx_compat());
+
+        let mut config = test_config();
+        config
+            .compat
+            .override_by_env(&env(&[("CNOSDB_INFLUX_COMPAT", "true")]))
+            .unwrap();
+        assert!(config.compat.influx_compat());
+    }
+
+    #[test]
+    fn test_override_by_env_invalid_value() {
+        let mut config = test_config();
+        let err = config
+            .override_by_env(&env(&[("CNOSDB_MAX_WRITE_LINES", "many")]))
+            .unwrap_err();
+        assert_eq!(err.key, "CNOSDB_MAX_WRITE_LINES");
+
+        // Overridden values are validated like the values of the file.
+        let mut config = test_config();
+        config
+            .override_by_env(&env(&[("CNOSDB_TIMEZONE", "Mars/Olympus")]))
+            .unwrap();
+        assert_eq!(config.query.default_timezone, "Mars/Olympus");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_get_config() {
+        let config = get_config("config.toml").unwrap();
+        assert_eq!(config.query.default_database(), "public");
+
+        let config = get_config_with_env(
+            "config.toml",
+            &env(&[("CNOSDB_DEFAULT_DATABASE", "metrics")]),
+        )
+        .unwrap();
+        assert_eq!(config.query.default_database(), "metrics");
+
+        let err = get_config_with_env("config.toml", &env(&[("CNOSDB_DEFAULT_DATABASE", "")]))
+            .unwrap_err();
+        assert_eq!(err.key, "query.default_database");
+
+        assert!(get_config("not_exist.toml").is_err());
+    }
+
+    #[test]
+    fn test_unknown_enum_values_rejected() {
+        let cases = [
+            ("storage", "flush_clock = 'tsc'", "storage.flush_clock"),
+            (
+                "storage",
+                "precision_rounding = 'floor'",
+                "storage.precision_rounding",
+            ),
+            ("storage", "engine = 'rocksdb'", "storage.engine"),
+            ("wal", "compression = 'gzip'", "wal.compression"),
+            (
+                "query",
+                "write_consistency = 'two'",
+                "query.write_consistency",
+            ),
+        ];
+        for (section, line, key) in cases {
+            let config = test_config_with(section, line);
+            assert_eq!(config.validate().unwrap_err().key, key);
+        }
+
+        let config_str = format!(
+            "{}\n[[access_rule]]\nclient = '*'\ndatabase = '*'\npermission = 'admin'\n",
+            TEST_CONFIG
+        );
+        let config: Config = toml::from_str(&config_str).unwrap();
+        assert_eq!(config.validate().unwrap_err().key, "access_rule.permission");
+
+        // Misspelled values from the environment don't fall back to the defaults either
+        let overrides = [
+            ("CNOSDB_STORAGE_FLUSH_CLOCK", "tsc", "storage.flush_clock"),
+            (
+                "CNOSDB_STORAGE_PRECISION_ROUNDING",
+                "floor",
+                "storage.precision_rounding",
+            ),
+            ("CNOSDB_STORAGE_ENGINE", "rocksdb", "storage.engine"),
+            ("CNOSDB_WAL_COMPRESSION", "gzip", "wal.compression"),
+            ("CNOSDB_WRITE_CONSISTENCY", "two", "query.write_consistency"),
+        ];
+        for (var, value, key) in overrides {
+            let err = get_config_with_env("config.toml", &env(&[(var, value)])).unwrap_err();
+            assert_eq!(err.key, key);
+        }
+    }
 }
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use config::QueryConfig;
+use parking_lot::Mutex;
+
+/// Tracks the open connections to the server and to each database,
+/// admitting new ones by `QueryConfig::accepts_connection`
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    config: QueryConfig,
+    // open connections to the server, open connections to each database
+    connections: Mutex<(u32, HashMap<String, u32>)>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(config: QueryConfig) -> Self {
+        Self {
+            config,
+            connections: Mutex::new((0, HashMap::new())),
+        }
+    }
+
+    /// Open a connection to `db`, None if a limit is reached.
+    /// The connection is closed when the returned guard is dropped.
+    pub fn try_open(self: &Arc<Self>, db: &str) -> Option<ConnectionGuard> {
+        let mut connections = self.connections.lock();
+        let db_connections = connections.1.get(db).copied().unwrap_or(0);
+        if !self
+            .config
+            .accepts_connection(connections.0, db_connections)
+        {
+            return None;
+        }
+        connections.0 += 1;
+        connections.1.insert(db.to_string(), db_connections + 1);
+        Some(ConnectionGuard {
+            limiter: self.clone(),
+            db: db.to_string(),
+        })
+    }
+
+    fn close(&self, db: &str) {
+        let mut connections = self.connections.lock();
+        connections.0 -= 1;
+        if let Some(db_connections) = connections.1.get_mut(db) {
+            *db_connections -= 1;
+            if *db_connections == 0 {
+                connections.1.remove(db);
+            }
+        }
+    }
+}
+
+pub struct ConnectionGuard {
+    limiter: Arc<ConnectionLimiter>,
+    db: String,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.limiter.close(&self.db);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use config::get_config;
+
+    use super::ConnectionLimiter;
+
+    #[test]
+    fn test_connection_limiter() {
+        let mut config = get_config("../config/config.toml").unwrap().query;
+        config.max_server_connections = 3;
+        config.max_connections_per_db = Some(1);
+        let limiter = Arc::new(ConnectionLimiter::new(config));
+
+        let db1 = limiter.try_open("db1").unwrap();
+        assert!(limiter.try_open("db1").is_none());
+        let _db2 = limiter.try_open("db2").unwrap();
+        let _db3 = limiter.try_open("db3").unwrap();
+        // The server limit is reached.
+        assert!(limiter.try_open("db4").is_none());
+
+        drop(db1);
+        let _db1 = limiter.try_open("db1").unwrap();
+    }
+}
@@ -1,22 +1,24 @@
-use std::{collections::HashMap, convert::Infallible, net::SocketAddr};
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::Arc};
 
-use http_protocol::header::{ACCEPT, AUTHORIZATION};
+use http_protocol::header::{ACCEPT, AUTHORIZATION, TRUNCATED};
 use http_protocol::parameter::{SqlParam, WriteParam};
 use http_protocol::response::ErrorResponse;
 
+use super::connection_limiter::ConnectionLimiter;
 use super::header::Header;
+use super::rate_limiter::RateLimiter;
 use super::Error as HttpError;
 use super::QuerySnafu;
 use crate::http::response::ResponseBuilder;
-use crate::http::result_format::fetch_record_batches;
 use crate::http::result_format::ResultFormat;
+use crate::http::result_format::{fetch_record_batches, is_truncated};
 use crate::http::Error;
 use crate::http::ParseLineProtocolSnafu;
 use crate::http::TskvSnafu;
 use crate::server;
 use crate::server::{Service, ServiceHandle};
 use chrono::Local;
-use config::TLSConfig;
+use config::{Config, TLSConfig};
 use datafusion::parquet::data_type::AsBytes;
 use flatbuffers::FlatBufferBuilder;
 use line_protocol::{line_protocol_to_lines, Line};
@@ -34,6 +36,7 @@ use tokio::sync::oneshot;
 use trace::debug;
 use trace::info;
 use tskv::engine::EngineRef;
+use warp::http::HeaderValue;
 use warp::hyper::body::Bytes;
 use warp::hyper::Body;
 use warp::reject::MethodNotAllowed;
@@ -52,6 +55,13 @@ pub struct HttpService {
     handle: Option<ServiceHandle<()>>,
     query_body_limit: u64,
     write_body_limit: u64,
+    write_lines_limit: Option<u64>,
+    target_partitions: usize,
+    default_database: String,
+    default_timezone: String,
+    max_result_bytes: Option<u64>,
+    write_limiter: Option<Arc<RateLimiter>>,
+    connection_limiter: Arc<ConnectionLimiter>,
 }
 
 impl HttpService {
@@ -60,17 +70,27 @@ impl HttpService {
         kv_inst: EngineRef,
         addr: SocketAddr,
         tls_config: Option<TLSConfig>,
-        query_body_limit: u64,
-        write_body_limit: u64,
+        config: &Config,
     ) -> Self {
+        let query_config = &config.query;
         Self {
             tls_config,
             addr,
             dbms,
             kv_inst,
             handle: None,
-            query_body_limit,
-            write_body_limit,
+            query_body_limit: query_config.query_sql_limit,
+            write_body_limit: query_config.write_sql_limit,
+            write_lines_limit: query_config.max_write_lines(),
+            target_partitions: query_config.target_partitions(),
+            default_database: query_config.default_database().to_string(),
+            default_timezone: query_config.default_timezone().to_string(),
+            max_result_bytes: query_config.max_result_bytes(),
+            write_limiter: config
+                .write
+                .max_writes_per_sec()
+                .map(|max| Arc::new(RateLimiter::new(max))),
+            connection_limiter: Arc::new(ConnectionLimiter::new(query_config.clone())),
         }
     }
 
@@ -91,10 +111,34 @@ impl HttpService {
         let dbms = self.dbms.clone();
         warp::any().map(move || dbms.clone())
     }
+    fn with_default_database(
+        &self,
+    ) -> impl Filter<Extract = (String,), Error = Infallible> + Clone {
+        let default_database = self.default_database.clone();
+        warp::any().map(move || default_database.clone())
+    }
+    fn with_default_timezone(
+        &self,
+    ) -> impl Filter<Extract = (String,), Error = Infallible> + Clone {
+        let default_timezone = self.default_timezone.clone();
+        warp::any().map(move || default_timezone.clone())
+    }
     fn with_kv_inst(&self) -> impl Filter<Extract = (EngineRef,), Error = Infallible> + Clone {
         let kv_inst = self.kv_inst.clone();
         warp::any().map(move || kv_inst.clone())
     }
+    fn with_write_limiter(
+        &self,
+    ) -> impl Filter<Extract = (Option<Arc<RateLimiter>>,), Error = Infallible> + Clone {
+        let write_limiter = self.write_limiter.clone();
+        warp::any().map(move || write_limiter.clone())
+    }
+    fn with_connection_limiter(
+        &self,
+    ) -> impl Filter<Extract = (Arc<ConnectionLimiter>,), Error = Infallible> + Clone {
+        let connection_limiter = self.connection_limiter.clone();
+        warp::any().map(move || connection_limiter.clone())
+    }
 
     fn routes(
         &self,
@@ -118,6 +162,8 @@ impl HttpService {
 
     fn query(&self) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
         // let dbms = self.dbms.clone();
+        let target_partitions = self.target_partitions;
+        let max_result_bytes = self.max_result_bytes;
         warp::path!("api" / "v1" / "sql")
             .and(warp::post())
             .and(warp::body::content_length_limit(self.query_body_limit))
@@ -125,8 +171,17 @@ impl HttpService {
             .and(self.handle_header())
             .and(warp::query::<SqlParam>())
             .and(self.with_dbms())
+            .and(self.with_default_database())
+            .and(self.with_default_timezone())
+            .and(self.with_connection_limiter())
             .and_then(
-                |req: Bytes, header: Header, param: SqlParam, dbms: DBMSRef| async move {
+                move |req: Bytes,
+                      header: Header,
+                      param: SqlParam,
+                      dbms: DBMSRef,
+                      default_database: String,
+                      default_timezone: String,
+                      connection_limiter: Arc<ConnectionLimiter>| async move {
                     let start = Instant::now();
                     debug!(
                         "Receive http sql request, header: {:?}, param: {:?}",
@@ -134,14 +189,32 @@ impl HttpService {
                     );
 
                     // Parse req、header and param to construct query request
-                    let query = construct_query(req, &header, param).map_err(|e| {
+                    let query = construct_query(
+                        req,
+                        &header,
+                        param,
+                        target_partitions,
+                        default_database,
+                        default_timezone,
+                    );
+                    let query = query.map_err(|e| {
                         sample_query_read_duration("", "", false, 0.0);
                         reject::custom(e)
                     })?;
-                    let result = sql_handle(&query, header, dbms).await.map_err(|e| {
-                        trace::error!("Failed to handle http sql request, err: {}", e);
-                        reject::custom(e)
-                    });
+                    // Held until the query is answered
+                    let _connection = connection_limiter
+                        .try_open(query.context().database())
+                        .ok_or_else(|| {
+                            reject::custom(HttpError::TooManyConnections {
+                                db: query.context().database().to_string(),
+                            })
+                        })?;
+                    let result = sql_handle(&query, header, dbms, max_result_bytes)
+                        .await
+                        .map_err(|e| {
+                            trace::error!("Failed to handle http sql request, err: {}", e);
+                            reject::custom(e)
+                        });
                     let user = query.context().user_info().user.as_str();
                     let db = query.context().database();
 
@@ -159,6 +232,7 @@ impl HttpService {
     fn write_line_protocol(
         &self,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        let write_lines_limit = self.write_lines_limit;
         warp::path!("api" / "v1" / "write")
             .and(warp::post())
             .and(warp::body::content_length_limit(self.write_body_limit))
@@ -166,18 +240,38 @@ impl HttpService {
             .and(self.handle_header())
             .and(warp::query::<WriteParam>())
             .and(self.with_kv_inst())
+            .and(self.with_write_limiter())
             .and_then(
-                |req: Bytes, header: Header, param: WriteParam, kv_inst: EngineRef| async move {
+                move |req: Bytes,
+                      header: Header,
+                      param: WriteParam,
+                      kv_inst: EngineRef,
+                      write_limiter: Option<Arc<RateLimiter>>| async move {
                     let start = Instant::now();
                     let user = match header.try_get_basic_auth() {
                         Ok(u) => u.user,
                         Err(e) => return Err(reject::custom(e)),
                     };
+                    if let Some(limiter) = write_limiter {
+                        if !limiter.try_acquire() {
+                            return Err(reject::custom(HttpError::TooManyWrites {
+                                limit: limiter.max_per_sec(),
+                            }));
+                        }
+                    }
                     let db = &param.db;
                     let lines = String::from_utf8_lossy(req.as_ref());
                     let line_protocol_lines =
                         line_protocol_to_lines(&lines, Local::now().timestamp_nanos())
                             .context(ParseLineProtocolSnafu)?;
+                    if let Some(limit) = write_lines_limit {
+                        if line_protocol_lines.len() as u64 > limit {
+                            return Err(reject::custom(HttpError::TooManyLines {
+                                lines: line_protocol_lines.len(),
+                                limit,
+                            }));
+                        }
+                    }
                     let points = parse_lines_to_points(db, &line_protocol_lines)?;
                     let req = WritePointsRpcRequest { version: 1, points };
                     let resp = kv_inst.write(req).await.context(TskvSnafu);
@@ -314,12 +408,21 @@ fn parse_lines_to_points(db: &str, lines: &[Line]) -> Result<Vec<u8>, Error> {
     Ok(fbb.finished_data().to_vec())
 }
 
-fn construct_query(req: Bytes, header: &Header, param: SqlParam) -> Result<Query, HttpError> {
+fn construct_query(
+    req: Bytes,
+    header: &Header,
+    param: SqlParam,
+    default_target_partitions: usize,
+    default_database: String,
+    default_timezone: String,
+) -> Result<Query, HttpError> {
     let user_info = header.try_get_basic_auth()?;
 
+    let target_partitions = param.target_partitions.unwrap_or(default_target_partitions);
     let context = ContextBuilder::new(user_info)
-        .with_database(param.db)
-        .with_target_partitions(param.target_partitions)
+        .with_database(Some(param.db.unwrap_or(default_database)))
+        .with_target_partitions(Some(target_partitions))
+        .with_time_zone(Some(default_timezone))
         .build();
 
     Ok(Query::new(
@@ -328,7 +431,12 @@ fn construct_query(req: Bytes, header: &Header, param: SqlParam) -> Result<Query
     ))
 }
 
-async fn sql_handle(query: &Query, header: Header, dbms: DBMSRef) -> Result<Response, HttpError> {
+async fn sql_handle(
+    query: &Query,
+    header: Header,
+    dbms: DBMSRef,
+    max_result_bytes: Option<u64>,
+) -> Result<Response, HttpError> {
     debug!("prepare to execute: {:?}", query.content());
 
     let fmt = ResultFormat::try_from(header.get_accept())?;
@@ -341,7 +449,12 @@ async fn sql_handle(query: &Query, header: Header, dbms: DBMSRef) -> Result<Resp
             reason: format!("{}", e),
         })?;
 
-    fmt.wrap_batches_to_response(&batches)
+    let mut resp = fmt.wrap_batches_to_response(&batches, max_result_bytes)?;
+    if is_truncated(&mut result) {
+        resp.headers_mut()
+            .insert(TRUNCATED, HeaderValue::from_static("true"));
+    }
+    Ok(resp)
 }
 
 /*************** top ****************/
@@ -11,12 +11,14 @@ use warp::reject;
 use warp::reply::Response;
 
 use http_protocol::response::ErrorResponse;
-use http_protocol::status_code::UNPROCESSABLE_ENTITY;
+use http_protocol::status_code::{TOO_MANY_REQUESTS, UNPROCESSABLE_ENTITY};
 
 use self::response::ResponseBuilder;
 
+mod connection_limiter;
 mod header;
 pub mod http_service;
+mod rate_limiter;
 mod response;
 mod result_format;
 
@@ -29,6 +31,15 @@ pub enum Error {
     #[snafu(display("Body oversize: {}", size))]
     BodyOversize { size: usize },
 
+    #[snafu(display("Too many lines: {}, limit: {}", lines, limit))]
+    TooManyLines { lines: usize, limit: u64 },
+
+    #[snafu(display("Too many writes, limit: {} per second", limit))]
+    TooManyWrites { limit: u64 },
+
+    #[snafu(display("Too many connections to database '{}'", db))]
+    TooManyConnections { db: String },
+
     #[snafu(display("Message is not valid UTF-8"))]
     NotUtf8,
 
@@ -83,11 +94,18 @@ impl From<&Error> for Response {
 
                 ResponseBuilder::new(UNPROCESSABLE_ENTITY).json(&error_resp)
             }
-            Error::InvalidHeader { reason: _ } | Error::ParseAuth { reason: _ } => {
+            Error::InvalidHeader { reason: _ }
+            | Error::ParseAuth { reason: _ }
+            | Error::TooManyLines { .. } => {
                 let error_resp = ErrorResponse::new(ErrorCode::Unknown, error_message);
 
                 ResponseBuilder::bad_request(&error_resp)
             }
+            Error::TooManyWrites { .. } | Error::TooManyConnections { .. } => {
+                let error_resp = ErrorResponse::new(ErrorCode::Unknown, error_message);
+
+                ResponseBuilder::new(TOO_MANY_REQUESTS).json(&error_resp)
+            }
             _ => ResponseBuilder::internal_server_error(),
         }
     }
@@ -179,4 +197,15 @@ mod tests {
 
         assert_eq!(content_type, HeaderValue::from_static(APPLICATION_JSON));
     }
+
+    #[test]
+    fn test_too_many_writes_error() {
+        let resp: Response = Error::TooManyWrites { limit: 10 }.into();
+
+        assert_eq!(resp.status(), TOO_MANY_REQUESTS);
+
+        let content_type = resp.headers().get(CONTENT_TYPE).unwrap();
+
+        assert_eq!(content_type, HeaderValue::from_static(APPLICATION_JSON));
+    }
 }
@@ -0,0 +1,68 @@
+use std::time::Instant;
+
+use parking_lot::Mutex;
+
+/// Token bucket limiter, tokens are refilled at `max_per_sec` a second and
+/// at most `max_per_sec` of them are kept, which bounds the burst size.
+#[derive(Debug)]
+pub struct RateLimiter {
+    max_per_sec: u64,
+    // last refill time, tokens left in the bucket
+    bucket: Mutex<(Instant, f64)>,
+}
+
+impl RateLimiter {
+    pub fn new(max_per_sec: u64) -> Self {
+        Self {
+            max_per_sec,
+            bucket: Mutex::new((Instant::now(), max_per_sec as f64)),
+        }
+    }
+
+    pub fn max_per_sec(&self) -> u64 {
+        self.max_per_sec
+    }
+
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// Admit a request arrived at `now`, false if the bucket has no token left
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let capacity = self.max_per_sec as f64;
+        let mut bucket = self.bucket.lock();
+        let elapsed = now.saturating_duration_since(bucket.0).as_secs_f64();
+        *bucket = (now, (bucket.1 + elapsed * capacity).min(capacity));
+        if bucket.1 < 1.0 {
+            return false;
+        }
+        bucket.1 -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RateLimiter;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(2);
+        let start = Instant::now();
+        assert!(limiter.try_acquire_at(start));
+        assert!(limiter.try_acquire_at(start + Duration::from_millis(10)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(20)));
+
+        // A token is refilled every half a second.
+        assert!(limiter.try_acquire_at(start + Duration::from_millis(520)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(530)));
+
+        // No more than a second of tokens is kept, so the burst is bounded.
+        let later = start + Duration::from_secs(5);
+        assert!(limiter.try_acquire_at(later));
+        assert!(limiter.try_acquire_at(later));
+        assert!(!limiter.try_acquire_at(later));
+    }
+}
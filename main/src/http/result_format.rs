@@ -79,13 +79,29 @@ impl ResultFormat {
         }
     }
 
-    pub fn wrap_batches_to_response(&self, batches: &[RecordBatch]) -> Result<Response, HttpError> {
+    pub fn wrap_batches_to_response(
+        &self,
+        batches: &[RecordBatch],
+        max_result_bytes: Option<u64>,
+    ) -> Result<Response, HttpError> {
         let result = self
             .format_batches(batches)
             .map_err(|e| HttpError::FetchResult {
                 reason: format!("{}", e),
             })?;
 
+        if let Some(limit) = max_result_bytes {
+            if result.len() as u64 > limit {
+                return Err(HttpError::FetchResult {
+                    reason: format!(
+                        "result size {} bytes exceeds the limit {} bytes",
+                        result.len(),
+                        limit
+                    ),
+                });
+            }
+        }
+
         let resp = ResponseBuilder::new(OK)
             .insert_header((CONTENT_TYPE, self.get_http_content_type()))
             .build(result);
@@ -128,7 +144,7 @@ pub async fn fetch_record_batches(res: &mut QueryHandle) -> ArrowResult<Vec<Reco
 
     for ele in res.result().iter_mut() {
         match ele {
-            Output::StreamData(stream) => {
+            Output::StreamData(stream) | Output::TruncatedStreamData(stream) => {
                 actual.append(stream);
             }
             Output::Nil(_) => {}
@@ -140,12 +156,20 @@ pub async fn fetch_record_batches(res: &mut QueryHandle) -> ArrowResult<Vec<Reco
     Ok(actual)
 }
 
+/// Whether the query timed out and its result only holds the batches completed before
+pub fn is_truncated(res: &mut QueryHandle) -> bool {
+    res.result()
+        .iter()
+        .any(|output| matches!(output, Output::TruncatedStreamData(_)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use datafusion::arrow::array::Int32Array;
     use datafusion::arrow::datatypes::{DataType, Field, Schema};
     use datafusion::from_slice::FromSlice;
+    use spi::service::protocol::{ContextBuilder, Query, QueryId, UserInfo};
     use std::sync::Arc;
 
     #[test]
@@ -215,4 +239,32 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_truncated_result() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from_slice([1, 2, 3]))])
+            .unwrap();
+        let user = UserInfo {
+            user: "root".to_string(),
+            password: "".to_string(),
+        };
+        let query = Query::new(ContextBuilder::new(user).build(), "select a".to_string());
+
+        let mut handle = QueryHandle::new(
+            QueryId::next_id(),
+            query.clone(),
+            vec![Output::StreamData(vec![batch.clone()])],
+        );
+        assert!(!is_truncated(&mut handle));
+
+        let mut handle = QueryHandle::new(
+            QueryId::next_id(),
+            query,
+            vec![Output::TruncatedStreamData(vec![batch])],
+        );
+        assert!(is_truncated(&mut handle));
+        let batches = fetch_record_batches(&mut handle).await.unwrap();
+        assert_eq!(batches.len(), 1);
+    }
 }
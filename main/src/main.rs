@@ -1,9 +1,10 @@
 use clap::{Parser, Subcommand};
+use config::EnvOverrides;
 use once_cell::sync::Lazy;
 use query::instance::make_cnosdbms;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::runtime::Runtime;
-use trace::{info, init_global_tracing};
+use trace::{error, info, init_global_tracing};
 use tskv::TsKv;
 mod http;
 mod report;
@@ -107,7 +108,9 @@ fn main() -> Result<(), std::io::Error> {
         "params: host:{}, http_host: {}, cpu:{:?}, memory:{:?}, config: {:?}, sub:{:?}",
         cli.grpc_host, cli.http_host, cli.cpu, cli.memory, cli.config, cli.subcmd
     );
-    let global_config = config::get_config(cli.config.as_str());
+    let global_config =
+        config::get_config_with_env(cli.config.as_str(), &EnvOverrides::from_process())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
     let mut _trace_guard = init_global_tracing(
         &global_config.log.path,
         "tsdb.log",
@@ -125,6 +128,17 @@ fn main() -> Result<(), std::io::Error> {
 
     init_tskv_metrics_recorder();
 
+    if global_config.storage.startup_self_check() {
+        if let Err(err) = global_config.run_self_check() {
+            error!(
+                "Startup self-check failed on '{}': {}",
+                err.path.display(),
+                err.reason
+            );
+            std::process::exit(1);
+        }
+    }
+
     runtime.clone().block_on(async move {
         match &cli.subcmd {
             SubCommand::Debug { debug: _ } => {
@@ -141,8 +155,7 @@ fn main() -> Result<(), std::io::Error> {
                     kv_inst.clone(),
                     http_host,
                     global_config.security.tls_config.clone(),
-                    global_config.query.query_sql_limit,
-                    global_config.query.write_sql_limit,
+                    &global_config,
                 ));
                 let grpc_service = Box::new(GrpcService::new(
                     dbms.clone(),
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use datafusion::{scheduler::Scheduler, sql::planner::ContextProvider};
@@ -21,6 +22,7 @@ use spi::{
 
 use spi::query::QueryError::{self, BuildQueryDispatcher};
 use spi::query::{LogicalPlannerSnafu, Result};
+use trace::{info_span, Instrument, Span};
 
 use crate::metadata::MetadataProvider;
 use crate::{
@@ -39,6 +41,10 @@ pub struct SimpleQueryDispatcher {
     parser: Arc<dyn Parser + Send + Sync>,
     // get query execution factory
     query_execution_factory: Arc<dyn QueryExecutionFactory + Send + Sync>,
+    // maximum nesting depth of expressions in a plan
+    max_expr_depth: Option<u32>,
+    // emit spans around the parse, plan and execute phases
+    query_tracing: bool,
 }
 
 #[async_trait]
@@ -72,9 +78,14 @@ impl QueryDispatcher for SimpleQueryDispatcher {
             .with_database(session.database());
         let scheme_provider = MetadataProvider::new(metadata.clone());
 
-        let logical_planner = DefaultLogicalPlanner::new(scheme_provider);
+        let mut logical_planner = DefaultLogicalPlanner::new(scheme_provider);
+        if let Some(depth) = self.max_expr_depth {
+            logical_planner = logical_planner.with_max_expr_depth(depth);
+        }
 
-        let statements = self.parser.parse(query.content())?;
+        let statements = self
+            .phase_span("parse", query_id)
+            .in_scope(|| self.parser.parse(query.content()))?;
 
         // not allow multi statement
         if statements.len() > 1 {
@@ -130,10 +141,14 @@ impl SimpleQueryDispatcher {
         logical_planner: &DefaultLogicalPlanner<S>,
         query_state_machine: Arc<QueryStateMachine>,
     ) -> Result<Output> {
+        let query_id = query_state_machine.query_id;
         // begin analyze
         query_state_machine.begin_analyze();
-        let logical_plan = logical_planner
-            .create_logical_plan(stmt.clone(), &query_state_machine.session)
+        let logical_plan = self
+            .phase_span("plan", query_id)
+            .in_scope(|| {
+                logical_planner.create_logical_plan(stmt.clone(), &query_state_machine.session)
+            })
             .context(LogicalPlannerSnafu)?;
         query_state_machine.end_analyze();
 
@@ -143,10 +158,20 @@ impl SimpleQueryDispatcher {
 
         // TrackedQuery.drop() is called implicitly when the value goes out of scope,
         self.query_tracker
-            .try_track_query(query_state_machine.query_id, execution)?
+            .try_track_query(query_id, execution)?
             .start()
+            .instrument(self.phase_span("execute", query_id))
             .await
     }
+
+    /// Span of a phase of the query, disabled unless query tracing is on
+    fn phase_span(&self, phase: &str, query_id: QueryId) -> Span {
+        if self.query_tracing {
+            info_span!("query", phase, query_id = ?query_id)
+        } else {
+            Span::none()
+        }
+    }
 }
 
 #[derive(Default)]
@@ -160,6 +185,10 @@ pub struct SimpleQueryDispatcherBuilder {
     scheduler: Option<Arc<Scheduler>>,
 
     queries_limit: usize,
+    max_expr_depth: Option<u32>,
+    query_tracing: bool,
+    query_timeout: Option<Duration>,
+    partial_results_on_timeout: bool,
 }
 
 impl SimpleQueryDispatcherBuilder {
@@ -193,6 +222,27 @@ impl SimpleQueryDispatcherBuilder {
         self
     }
 
+    pub fn with_max_expr_depth(mut self, depth: u32) -> Self {
+        self.max_expr_depth = Some(depth);
+        self
+    }
+
+    pub fn with_query_tracing(mut self, enabled: bool) -> Self {
+        self.query_tracing = enabled;
+        self
+    }
+
+    /// Maximum execution time of a query, queries are not timed out if None
+    pub fn with_query_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.query_timeout = timeout;
+        self
+    }
+
+    pub fn with_partial_results_on_timeout(mut self, enabled: bool) -> Self {
+        self.partial_results_on_timeout = enabled;
+        self
+    }
+
     pub fn build(self) -> Result<SimpleQueryDispatcher> {
         let metadata = self.metadata.ok_or_else(|| BuildQueryDispatcher {
             err: "lost of metadata".to_string(),
@@ -215,11 +265,10 @@ impl SimpleQueryDispatcherBuilder {
 
         let query_tracker = Arc::new(QueryTracker::new(self.queries_limit));
 
-        let query_execution_factory = Arc::new(SqlQueryExecutionFactory::new(
-            optimizer,
-            scheduler,
-            query_tracker.clone(),
-        ));
+        let query_execution_factory = Arc::new(
+            SqlQueryExecutionFactory::new(optimizer, scheduler, query_tracker.clone())
+                .with_query_timeout(self.query_timeout, self.partial_results_on_timeout),
+        );
 
         Ok(SimpleQueryDispatcher {
             metadata,
@@ -227,6 +276,8 @@ impl SimpleQueryDispatcherBuilder {
             parser,
             query_execution_factory,
             query_tracker,
+            max_expr_depth: self.max_expr_depth,
+            query_tracing: self.query_tracing,
         })
     }
 }
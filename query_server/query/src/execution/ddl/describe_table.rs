@@ -49,7 +49,7 @@ fn describe_table(table_name: &str, catalog: MetaDataRef) -> Result<Output, Exec
 
             tskv_schema.columns().iter().for_each(|column| {
                 name.append_value(column.name.as_str());
-                data_type.append_value(column.column_type.to_sql_type_str());
+                data_type.append_value(column.column_type.sql_type_name());
                 column_type.append_value(column.column_type.as_column_type_str());
                 encoding.append_value(column.encoding.as_str());
             });
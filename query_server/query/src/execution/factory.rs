@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::{dispatcher::query_tracker::QueryTracker, execution::ddl::DDLExecution};
 use datafusion::scheduler::Scheduler;
@@ -15,6 +16,10 @@ pub struct SqlQueryExecutionFactory {
     // TODO 需要封装 scheduler
     scheduler: Arc<Scheduler>,
     query_tracker: Arc<QueryTracker>,
+    // maximum execution time of a query
+    query_timeout: Option<Duration>,
+    // return the batches completed before the timeout instead of an error
+    partial_results_on_timeout: bool,
 }
 
 impl SqlQueryExecutionFactory {
@@ -28,8 +33,20 @@ impl SqlQueryExecutionFactory {
             optimizer,
             scheduler,
             query_tracker,
+            query_timeout: None,
+            partial_results_on_timeout: false,
         }
     }
+
+    pub fn with_query_timeout(
+        mut self,
+        timeout: Option<Duration>,
+        partial_results_on_timeout: bool,
+    ) -> Self {
+        self.query_timeout = timeout;
+        self.partial_results_on_timeout = partial_results_on_timeout;
+        self
+    }
 }
 
 impl QueryExecutionFactory for SqlQueryExecutionFactory {
@@ -44,6 +61,8 @@ impl QueryExecutionFactory for SqlQueryExecutionFactory {
                 query_plan,
                 self.optimizer.clone(),
                 self.scheduler.clone(),
+                self.query_timeout,
+                self.partial_results_on_timeout,
             )),
             Plan::DDL(ddl_plan) => Arc::new(DDLExecution::new(state_machine, ddl_plan)),
             Plan::SYSTEM(sys_plan) => Arc::new(SystemExecution::new(
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use datafusion::scheduler::Scheduler;
@@ -23,6 +24,8 @@ pub struct SqlQueryExecution {
     plan: QueryPlan,
     optimizer: Arc<dyn Optimizer + Send + Sync>,
     scheduler: Arc<Scheduler>,
+    query_timeout: Option<Duration>,
+    partial_results_on_timeout: bool,
 
     abort_handle: Mutex<Option<AbortHandle>>,
 }
@@ -33,12 +36,16 @@ impl SqlQueryExecution {
         plan: QueryPlan,
         optimizer: Arc<dyn Optimizer + Send + Sync>,
         scheduler: Arc<Scheduler>,
+        query_timeout: Option<Duration>,
+        partial_results_on_timeout: bool,
     ) -> Self {
         Self {
             query_state_machine,
             plan,
             optimizer,
             scheduler,
+            query_timeout,
+            partial_results_on_timeout,
             abort_handle: Mutex::new(None),
         }
     }
@@ -56,22 +63,45 @@ impl SqlQueryExecution {
 
         // begin schedule
         self.query_state_machine.begin_schedule();
-        let execution_result = self
+        let mut stream = self
             .scheduler
             .schedule(
                 optimized_physical_plan,
                 self.query_state_machine.session.inner().task_ctx(),
             )
             .context(ScheduleSnafu)?
-            .stream()
-            .try_collect::<Vec<_>>()
-            .await
-            .map_err(|source| QueryError::Execution {
-                source: ExecutionError::Arrow { source },
-            })?;
+            .stream();
+        // Collect batch by batch, so the ones completed before a timeout are kept
+        let mut batches = vec![];
+        let collect = async {
+            while let Some(batch) =
+                stream
+                    .try_next()
+                    .await
+                    .map_err(|source| QueryError::Execution {
+                        source: ExecutionError::Arrow { source },
+                    })?
+            {
+                batches.push(batch);
+            }
+            Ok::<_, QueryError>(())
+        };
+        let execution_result = match self.query_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, collect).await {
+                Ok(result) => result,
+                Err(_) => Err(QueryError::Timeout { timeout }),
+            },
+            None => collect.await,
+        };
         self.query_state_machine.end_schedule();
 
-        Ok(Output::StreamData(execution_result))
+        match execution_result {
+            Ok(()) => Ok(Output::StreamData(batches)),
+            Err(QueryError::Timeout { .. }) if self.partial_results_on_timeout => {
+                Ok(Output::TruncatedStreamData(batches))
+            }
+            Err(err) => Err(err),
+        }
     }
 }
 
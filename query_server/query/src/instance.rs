@@ -92,6 +92,10 @@ pub fn make_cnosdbms(engine: EngineRef, options: Options) -> Result<Cnosdbms> {
         .with_optimizer(optimizer)
         .with_scheduler(scheduler)
         .with_queries_limit(queries_limit)
+        .with_max_expr_depth(options.query.max_expr_depth)
+        .with_query_tracing(options.query.query_tracing)
+        .with_query_timeout(options.query.query_timeout)
+        .with_partial_results_on_timeout(options.query.partial_results_on_timeout)
         .build()
         .context(BuildSnafu)?;
 
@@ -149,7 +153,7 @@ mod tests {
 
         for ele in result.result().iter_mut() {
             match ele {
-                Output::StreamData(data) => {
+                Output::StreamData(data) | Output::TruncatedStreamData(data) => {
                     actual.append(data);
                 }
                 Output::Nil(_) => {
@@ -163,7 +167,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_simple_sql() {
-        let config = get_config("../../config/config.toml");
+        let config = get_config("../../config/config.toml").unwrap();
         let opt = Options::from(&config);
         let db = make_cnosdbms(Arc::new(MockEngine::default()), opt).unwrap();
 
@@ -218,7 +222,7 @@ mod tests {
     #[ignore]
     async fn test_topk_sql() {
         // trace::init_default_global_tracing("/tmp", "test_rust.log", "debug");
-        let config = get_config("../../config/config.toml");
+        let config = get_config("../../config/config.toml").unwrap();
         let opt = Options::from(&config);
         let db = make_cnosdbms(Arc::new(MockEngine::default()), opt).unwrap();
 
@@ -252,7 +256,7 @@ mod tests {
     #[tokio::test]
     async fn test_topk_desc_sql() {
         // trace::init_default_global_tracing("/tmp", "test_rust.log", "debug");
-        let config = get_config("../../config/config.toml");
+        let config = get_config("../../config/config.toml").unwrap();
         let opt = Options::from(&config);
         let db = make_cnosdbms(Arc::new(MockEngine::default()), opt).unwrap();
 
@@ -286,7 +290,7 @@ mod tests {
     #[tokio::test]
     #[ignore]
     async fn test_create_external_csv_table() {
-        let config = get_config("../../config/config.toml");
+        let config = get_config("../../config/config.toml").unwrap();
         let opt = Options::from(&config);
         let db = make_cnosdbms(Arc::new(MockEngine::default()), opt).unwrap();
 
@@ -340,7 +344,7 @@ mod tests {
     #[tokio::test]
     #[ignore]
     async fn test_create_external_parquet_table() {
-        let config = get_config("../../config/config.toml");
+        let config = get_config("../../config/config.toml").unwrap();
         let opt = Options::from(&config);
         let db = make_cnosdbms(Arc::new(MockEngine::default()), opt).unwrap();
 
@@ -386,7 +390,7 @@ mod tests {
     #[tokio::test]
     #[ignore]
     async fn test_create_external_json_table() {
-        let config = get_config("../config/config.toml");
+        let config = get_config("../config/config.toml").unwrap();
         let opt = Options::from(&config);
         let db = make_cnosdbms(Arc::new(MockEngine::default()), opt).unwrap();
 
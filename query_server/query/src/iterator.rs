@@ -471,7 +471,14 @@ impl RowIterator {
             return Ok(val.clone());
         }
 
-        let tsm_reader = TsmReader::open(file.file_path()).await?;
+        let verify_checksums = self
+            .version
+            .as_ref()
+            .map(|version| version.storage_opt.verify_checksums_on_read)
+            .unwrap_or(false);
+        let tsm_reader = TsmReader::open(file.file_path())
+            .await?
+            .with_verify_checksums(verify_checksums);
         self.open_files.insert(file.file_id(), tsm_reader.clone());
 
         Ok(tsm_reader)
@@ -15,7 +15,9 @@ use datafusion::scalar::ScalarValue;
 use datafusion::sql::parser::CreateExternalTable as AstCreateExternalTable;
 use datafusion::sql::planner::{ContextProvider, SqlToRel};
 use datafusion::sql::sqlparser::ast::{
-    DataType as SQLDataType, Ident, ObjectName, Query, Statement,
+    DataType as SQLDataType, Expr as SQLExpr, FunctionArg, FunctionArgExpr, Ident, JoinConstraint,
+    JoinOperator, ObjectName, Query, Select, SelectItem, SetExpr, Statement, TableFactor,
+    TableWithJoins,
 };
 use datafusion::sql::TableReference;
 use models::schema::{ColumnType, TableColumn, TIME_FIELD_NAME};
@@ -52,18 +54,31 @@ use spi::query::logical_planner::MetadataSnafu;
 #[derive(Debug)]
 pub struct SqlPlaner<S> {
     schema_provider: S,
+    max_expr_depth: Option<u32>,
 }
 
 impl<S: ContextProvider> SqlPlaner<S> {
     /// Create a new query planner
     pub fn new(schema_provider: S) -> Self {
-        SqlPlaner { schema_provider }
+        SqlPlaner {
+            schema_provider,
+            max_expr_depth: None,
+        }
+    }
+
+    /// Reject query plans with expressions nested deeper than `depth`
+    pub fn with_max_expr_depth(mut self, depth: u32) -> Self {
+        self.max_expr_depth = Some(depth);
+        self
     }
 
     /// Generate a logical plan from an  Extent SQL statement
     pub(crate) fn statement_to_plan(&self, statement: ExtStatement) -> Result<Plan> {
         match statement {
-            ExtStatement::SqlStatement(stmt) => self.df_sql_to_plan(*stmt),
+            ExtStatement::SqlStatement(stmt) => {
+                self.check_expr_depth(&stmt)?;
+                self.df_sql_to_plan(*stmt)
+            }
             ExtStatement::CreateExternalTable(stmt) => self.external_table_to_plan(stmt),
             ExtStatement::CreateTable(stmt) => self.create_table_to_plan(stmt),
             ExtStatement::CreateDatabase(stmt) => self.database_to_plan(stmt),
@@ -110,6 +125,20 @@ impl<S: ContextProvider> SqlPlaner<S> {
         }
     }
 
+    /// Check the expression depth on the AST, before the recursive planner can overflow the stack
+    fn check_expr_depth(&self, stmt: &Statement) -> Result<()> {
+        let max_depth = match self.max_expr_depth {
+            Some(depth) => depth,
+            None => return Ok(()),
+        };
+        if statement_expr_depth(stmt, max_depth) > max_depth {
+            return Err(LogicalPlannerError::Semantic {
+                err: format!("expression nesting depth exceeds the limit {}", max_depth),
+            });
+        }
+        Ok(())
+    }
+
     /// Generate a plan for EXPLAIN ... that will print out a plan
     ///
     pub fn explain_statement_to_plan(
@@ -641,6 +670,169 @@ fn semantic_check(
     Ok(())
 }
 
+/// Nesting depth of `expr`, a leaf expression like a column or a literal has depth 1.
+/// Expressions of a subquery are nested in the expression holding the subquery.
+pub fn count_expr_depth(expr: &SQLExpr) -> u32 {
+    expr_depth(expr, u32::MAX)
+}
+
+/// Deepest expression nesting of `stmt`, counted as `count_expr_depth` does.
+/// The walk stops descending once `limit` is crossed, so the returned depth is at most `limit + 1`
+/// and its own recursion is bounded by `limit`.
+fn statement_expr_depth(stmt: &Statement, limit: u32) -> u32 {
+    let depth = |expr: &SQLExpr| expr_depth(expr, limit);
+    match stmt {
+        Statement::Query(query) => query_expr_depth(query, limit),
+        Statement::Insert { source, .. } => query_expr_depth(source, limit),
+        Statement::Explain { statement, .. } => statement_expr_depth(statement, limit),
+        Statement::Update {
+            assignments,
+            selection,
+            ..
+        } => assignments
+            .iter()
+            .map(|assignment| depth(&assignment.value))
+            .chain(selection.iter().map(depth))
+            .max()
+            .unwrap_or(0),
+        Statement::Delete { selection, .. } => selection.iter().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn query_expr_depth(query: &Query, limit: u32) -> u32 {
+    let depth = |expr: &SQLExpr| expr_depth(expr, limit);
+    let with_depth = query.with.iter().flat_map(|with| {
+        with.cte_tables
+            .iter()
+            .map(|cte| query_expr_depth(&cte.query, limit))
+    });
+    with_depth
+        .chain(Some(set_expr_depth(&query.body, limit)))
+        .chain(query.order_by.iter().map(|order_by| depth(&order_by.expr)))
+        .chain(query.limit.iter().map(depth))
+        .max()
+        .unwrap_or(0)
+}
+
+fn set_expr_depth(set_expr: &SetExpr, limit: u32) -> u32 {
+    match set_expr {
+        SetExpr::Select(select) => select_expr_depth(select, limit),
+        SetExpr::Query(query) => query_expr_depth(query, limit),
+        SetExpr::SetOperation { left, right, .. } => {
+            set_expr_depth(left, limit).max(set_expr_depth(right, limit))
+        }
+        SetExpr::Values(values) => values
+            .0
+            .iter()
+            .flatten()
+            .map(|expr| expr_depth(expr, limit))
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn select_expr_depth(select: &Select, limit: u32) -> u32 {
+    let depth = |expr: &SQLExpr| expr_depth(expr, limit);
+    let projection_depth = select.projection.iter().map(|item| match item {
+        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => depth(expr),
+        _ => 0,
+    });
+    projection_depth
+        .chain(
+            select
+                .from
+                .iter()
+                .map(|table| table_expr_depth(table, limit)),
+        )
+        .chain(select.selection.iter().map(depth))
+        .chain(select.group_by.iter().map(depth))
+        .chain(select.having.iter().map(depth))
+        .max()
+        .unwrap_or(0)
+}
+
+fn table_expr_depth(table: &TableWithJoins, limit: u32) -> u32 {
+    let factor_depth = |factor: &TableFactor| match factor {
+        TableFactor::Derived { subquery, .. } => query_expr_depth(subquery, limit),
+        _ => 0,
+    };
+    let joins_depth = table.joins.iter().map(|join| {
+        let constraint_depth = match &join.join_operator {
+            JoinOperator::Inner(JoinConstraint::On(expr))
+            | JoinOperator::LeftOuter(JoinConstraint::On(expr))
+            | JoinOperator::RightOuter(JoinConstraint::On(expr))
+            | JoinOperator::FullOuter(JoinConstraint::On(expr)) => expr_depth(expr, limit),
+            _ => 0,
+        };
+        constraint_depth.max(factor_depth(&join.relation))
+    });
+    joins_depth.fold(factor_depth(&table.relation), u32::max)
+}
+
+fn expr_depth(expr: &SQLExpr, limit: u32) -> u32 {
+    if limit == 0 {
+        // Already deeper than the limit, the exact depth does not matter
+        return 1;
+    }
+    let depth = |expr: &SQLExpr| expr_depth(expr, limit - 1);
+    let children_depth = match expr {
+        SQLExpr::BinaryOp { left, right, .. }
+        | SQLExpr::IsDistinctFrom(left, right)
+        | SQLExpr::IsNotDistinctFrom(left, right) => depth(left).max(depth(right)),
+        SQLExpr::UnaryOp { expr, .. }
+        | SQLExpr::Cast { expr, .. }
+        | SQLExpr::TryCast { expr, .. }
+        | SQLExpr::Extract { expr, .. }
+        | SQLExpr::IsNull(expr)
+        | SQLExpr::IsNotNull(expr)
+        | SQLExpr::Nested(expr) => depth(expr),
+        SQLExpr::Between {
+            expr, low, high, ..
+        } => depth(expr).max(depth(low)).max(depth(high)),
+        SQLExpr::Like { expr, pattern, .. } | SQLExpr::ILike { expr, pattern, .. } => {
+            depth(expr).max(depth(pattern))
+        }
+        SQLExpr::InList { expr, list, .. } => list.iter().map(depth).fold(depth(expr), u32::max),
+        SQLExpr::InSubquery { expr, subquery, .. } => {
+            depth(expr).max(query_expr_depth(subquery, limit - 1))
+        }
+        SQLExpr::Exists { subquery, .. } | SQLExpr::Subquery(subquery) => {
+            query_expr_depth(subquery, limit - 1)
+        }
+        SQLExpr::Case {
+            operand,
+            conditions,
+            results,
+            else_result,
+        } => operand
+            .iter()
+            .chain(else_result.iter())
+            .map(|expr| depth(expr))
+            .chain(conditions.iter().map(depth))
+            .chain(results.iter().map(depth))
+            .max()
+            .unwrap_or(0),
+        SQLExpr::Tuple(exprs) => exprs.iter().map(depth).max().unwrap_or(0),
+        SQLExpr::Function(function) => function
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Named {
+                    arg: FunctionArgExpr::Expr(expr),
+                    ..
+                }
+                | FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => depth(expr),
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    };
+    children_depth + 1
+}
+
 fn table_write_plan_node(
     table_name: String,
     target_table: Arc<dyn TableSource>,
@@ -696,6 +888,9 @@ mod tests {
     use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
     use datafusion::logical_expr::{Aggregate, AggregateUDF, ScalarUDF, TableSource};
     use datafusion::sql::planner::ContextProvider;
+    use datafusion::sql::sqlparser::dialect::GenericDialect;
+    use datafusion::sql::sqlparser::parser::Parser;
+    use datafusion::sql::sqlparser::tokenizer::Tokenizer;
     use datafusion::sql::TableReference;
     use std::any::Any;
     use std::ops::Deref;
@@ -799,49 +994,57 @@ mod tests {
                             id: 0,
                             name: "time".to_string(),
                             column_type: ColumnType::Time,
-                            encoding: Encoding::Default
+                            encoding: Encoding::Default,
+                            deleted: false,
                         },
                         TableColumn {
                             id: 1,
                             name: "column6".to_string(),
                             column_type: ColumnType::Tag,
                             encoding: Encoding::Default,
+                            deleted: false,
                         },
                         TableColumn {
                             id: 2,
                             name: "column7".to_string(),
                             column_type: ColumnType::Tag,
                             encoding: Encoding::Default,
+                            deleted: false,
                         },
                         TableColumn {
                             id: 3,
                             name: "column1".to_string(),
                             column_type: ColumnType::Field(ValueType::Integer),
                             encoding: Encoding::Delta,
+                            deleted: false,
                         },
                         TableColumn {
                             id: 4,
                             name: "column2".to_string(),
                             column_type: ColumnType::Field(ValueType::String),
                             encoding: Encoding::Gzip,
+                            deleted: false,
                         },
                         TableColumn {
                             id: 5,
                             name: "column3".to_string(),
                             column_type: ColumnType::Field(ValueType::Unsigned),
                             encoding: Encoding::Null,
+                            deleted: false,
                         },
                         TableColumn {
                             id: 6,
                             name: "column4".to_string(),
                             column_type: ColumnType::Field(ValueType::Boolean),
                             encoding: Encoding::Default,
+                            deleted: false,
                         },
                         TableColumn {
                             id: 7,
                             name: "column5".to_string(),
                             column_type: ColumnType::Field(ValueType::Float),
                             encoding: Encoding::Gorilla,
+                            deleted: false,
                         }
                     ],
                     name: "test".to_string(),
@@ -946,4 +1149,83 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    fn parse_expr(sql: &str) -> SQLExpr {
+        let dialect = GenericDialect {};
+        let tokens = Tokenizer::new(&dialect, sql).tokenize().unwrap();
+        Parser::new(tokens, &dialect).parse_expr().unwrap()
+    }
+
+    #[test]
+    fn test_count_expr_depth() {
+        assert_eq!(count_expr_depth(&parse_expr("a")), 1);
+        assert_eq!(count_expr_depth(&parse_expr("1")), 1);
+        assert_eq!(count_expr_depth(&parse_expr("a + 1")), 2);
+        assert_eq!(count_expr_depth(&parse_expr("(a + 1) * (b - 2)")), 4);
+        assert_eq!(count_expr_depth(&parse_expr("a + 1 + 2 + 3")), 4);
+        assert_eq!(count_expr_depth(&parse_expr("abs(a + 1)")), 3);
+        assert_eq!(
+            count_expr_depth(&parse_expr("a in (select b + 1 from t)")),
+            3
+        );
+    }
+
+    #[test]
+    fn test_statement_expr_depth_stops_at_limit() {
+        let mut expr = SQLExpr::Identifier(Ident::new("a"));
+        for _ in 0..100 {
+            expr = SQLExpr::Nested(Box::new(expr));
+        }
+        assert_eq!(count_expr_depth(&expr), 101);
+        assert_eq!(expr_depth(&expr, 101), 101);
+        assert_eq!(expr_depth(&expr, 10), 11);
+    }
+
+    #[test]
+    fn test_max_expr_depth() {
+        let sql = "select field_int + 1 + 2 + 3 from test_tb";
+
+        let mut statements = ExtParser::parse_sql(sql).unwrap();
+        let planner = SqlPlaner::new(MockContext {}).with_max_expr_depth(16);
+        let plan = planner.statement_to_plan(statements.pop_back().unwrap());
+        assert!(matches!(plan, Ok(Plan::Query(_))));
+
+        let mut statements = ExtParser::parse_sql(sql).unwrap();
+        let planner = SqlPlaner::new(MockContext {}).with_max_expr_depth(2);
+        let plan = planner.statement_to_plan(statements.pop_back().unwrap());
+        assert!(matches!(plan, Err(LogicalPlannerError::Semantic { .. })));
+    }
+
+    #[test]
+    fn test_max_expr_depth_checked_before_planning() {
+        // Far deeper than the recursive planner can handle, planning it would overflow the stack
+        let mut expr = SQLExpr::Identifier(Ident::new("field_int"));
+        for _ in 0..10_000 {
+            expr = SQLExpr::Nested(Box::new(expr));
+        }
+
+        let handle = std::thread::Builder::new()
+            .stack_size(16 * 1024 * 1024)
+            .spawn(move || {
+                let sql = "select field_int from test_tb where field_int > 0";
+                let mut statements = ExtParser::parse_sql(sql).unwrap();
+                let mut statement = statements.pop_back().unwrap();
+                match &mut statement {
+                    ExtStatement::SqlStatement(stmt) => match stmt.as_mut() {
+                        Statement::Query(query) => match query.body.as_mut() {
+                            SetExpr::Select(select) => select.selection = Some(expr),
+                            _ => panic!(),
+                        },
+                        _ => panic!(),
+                    },
+                    _ => panic!(),
+                }
+
+                let planner = SqlPlaner::new(MockContext {}).with_max_expr_depth(64);
+                let plan = planner.statement_to_plan(statement);
+                matches!(plan, Err(LogicalPlannerError::Semantic { .. }))
+            })
+            .unwrap();
+        assert!(handle.join().unwrap());
+    }
 }
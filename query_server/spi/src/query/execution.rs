@@ -52,6 +52,8 @@ pub trait QueryExecution: Send + Sync {
 // }
 pub enum Output {
     StreamData(Vec<RecordBatch>),
+    // batches completed before the query timed out
+    TruncatedStreamData(Vec<RecordBatch>),
     Nil(()),
 }
 
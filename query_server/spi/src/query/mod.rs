@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use datafusion::{
     arrow::datatypes::DataType, error::DataFusionError, sql::sqlparser::parser::ParserError,
 };
@@ -68,6 +70,9 @@ pub enum QueryError {
     #[snafu(display("The query has been canceled"))]
     Cancel,
 
+    #[snafu(display("The query timed out after {:?}", timeout))]
+    Timeout { timeout: Duration },
+
     #[snafu(display("The query server has been closed"))]
     Closed,
 }
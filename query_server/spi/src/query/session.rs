@@ -1,7 +1,8 @@
 use datafusion::{
-    config::OPT_OPTIMIZER_SKIP_FAILED_RULES,
+    config::{OPT_OPTIMIZER_SKIP_FAILED_RULES, OPT_TIME_ZONE},
     execution::context,
     prelude::{SessionConfig, SessionContext},
+    scalar::ScalarValue,
 };
 
 use crate::service::protocol::Context;
@@ -78,4 +79,13 @@ impl IsiphoSessionConfig {
         self.inner = self.inner.with_target_partitions(n);
         self
     }
+
+    /// Customize the time zone used by time functions, e.g. "Asia/Shanghai"
+    pub fn with_time_zone(self, time_zone: impl Into<String>) -> Self {
+        self.inner
+            .config_options
+            .write()
+            .set(OPT_TIME_ZONE, ScalarValue::Utf8(Some(time_zone.into())));
+        self
+    }
 }
@@ -93,6 +93,13 @@ impl ContextBuilder {
         self
     }
 
+    pub fn with_time_zone(mut self, time_zone: Option<String>) -> Self {
+        if let Some(time_zone) = time_zone {
+            self.session_config = self.session_config.with_time_zone(time_zone);
+        }
+        self
+    }
+
     pub fn build(self) -> Context {
         Context {
             user_info: self.user_info,
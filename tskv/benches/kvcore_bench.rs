@@ -9,7 +9,7 @@ use protos::{kv_service::WritePointsRpcRequest, models_helper};
 use tskv::{engine::Engine, TsKv};
 
 async fn get_tskv() -> TsKv {
-    let mut global_config = config::get_config("../config/config.toml");
+    let mut global_config = config::get_config("../config/config.toml").unwrap();
     global_config.wal.path = "/tmp/test_bench/wal".to_string();
     let opt = tskv::kv_option::Options::from(&global_config);
 
@@ -463,7 +463,9 @@ pub async fn run_compaction_job(
     let mut tsm_index_iters = Vec::new();
     for col_file in request.files.iter() {
         let tsm_file = col_file.file_path();
-        let tsm_reader = TsmReader::open(&tsm_file).await?;
+        let tsm_reader = TsmReader::open(&tsm_file)
+            .await?
+            .with_verify_checksums(storage_opt.verify_checksums_on_read);
         tsm_files.push(tsm_file);
         let idx_iter = tsm_reader.index_iterator().peekable();
         tsm_readers.push(tsm_reader);
@@ -611,8 +613,11 @@ mod test {
         kv_option::Options,
         summary::VersionEdit,
         tseries_family::{ColumnFile, LevelInfo, TimeRange, Version},
-        tsm::{self, codec::DataBlockEncoding, DataBlock, Tombstone, TsmReader, TsmTombstone},
-        TseriesFamilyId,
+        tsm::{
+            self, codec::DataBlockEncoding, DataBlock, ReadTsmError, Tombstone, TsmReader,
+            TsmTombstone,
+        },
+        Error, TseriesFamilyId,
     };
 
     async fn write_data_blocks_to_column_file(
@@ -699,7 +704,7 @@ mod test {
 
     fn create_options(base_dir: String) -> Arc<Options> {
         let dir = "../config/config.toml";
-        let mut config = config::get_config(dir);
+        let mut config = config::get_config(dir).unwrap();
         config.storage.path = base_dir;
         let opt = Options::from(&config);
         Arc::new(opt)
@@ -776,6 +781,55 @@ mod test {
         check_column_file(dir, version_edit, expected_data).await;
     }
 
+    #[tokio::test]
+    async fn test_compaction_verify_checksums() {
+        #[rustfmt::skip]
+        let data = vec![
+            HashMap::from([
+                (1, vec![DataBlock::I64 { ts: vec![1, 2, 3], val: vec![1, 2, 3], enc: DataBlockEncoding::default() }]),
+            ]),
+            HashMap::from([
+                (1, vec![DataBlock::I64 { ts: vec![4, 5, 6], val: vec![4, 5, 6], enc: DataBlockEncoding::default() }]),
+            ]),
+        ];
+
+        let dir = "/tmp/test/compaction/verify_checksums";
+        let database = "dba".to_string();
+        let mut config = config::get_config("../config/config.toml").unwrap();
+        config.storage.path = dir.to_string();
+        config.storage.verify_checksums_on_read = true;
+        let opt = Arc::new(Options::from(&config));
+        let dir = opt.storage.tsm_dir(&database, 1);
+
+        let (next_file_id, files) =
+            write_data_blocks_to_column_file(&dir, data, 1, opt.clone()).await;
+
+        // Flip the last byte of the values of the block in the first file.
+        let tsm_file = files[0].file_path();
+        let blk = TsmReader::open(&tsm_file)
+            .await
+            .unwrap()
+            .index_iterator()
+            .next()
+            .unwrap()
+            .block_iterator()
+            .next()
+            .unwrap();
+        let mut file_data = std::fs::read(&tsm_file).unwrap();
+        let pos = (blk.offset() + blk.size() - 1) as usize;
+        file_data[pos] = !file_data[pos];
+        std::fs::write(&tsm_file, file_data).unwrap();
+
+        let (compact_req, kernel) =
+            prepare_compact_req_and_kernel(database, opt, next_file_id, files);
+        assert!(matches!(
+            run_compaction_job(compact_req, kernel).await,
+            Err(Error::ReadTsm {
+                source: ReadTsmError::CrcCheck { .. }
+            })
+        ));
+    }
+
     #[tokio::test]
     async fn test_compaction_1() {
         #[rustfmt::skip]
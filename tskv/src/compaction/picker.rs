@@ -409,7 +409,7 @@ mod test {
 
     fn create_options(base_dir: String) -> Arc<Options> {
         let dir = "../config/config.toml";
-        let mut config = config::get_config(dir);
+        let mut config = config::get_config(dir).unwrap();
         config.storage.path = base_dir;
         let opt = Options::from(&config);
         Arc::new(opt)
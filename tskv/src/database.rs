@@ -195,7 +195,7 @@ impl Database {
                 Ok(_) => {}
                 Err(_) => {
                     self.index
-                        .check_field_type_or_else_add(sid, &point)
+                        .check_field_type_or_else_add(sid, &point, &self.opt.storage)
                         .context(error::IndexErrSnafu)?;
                 }
             }
@@ -255,6 +255,12 @@ impl Database {
         {
             return Err(InvalidPoint);
         }
+        if let Some(max) = self.opt.storage.max_tags_per_series {
+            let num = info.tags().ok_or(InvalidPoint)?.len();
+            if num > max as usize {
+                return Err(Error::TooManyTags { num, max });
+            }
+        }
         if let Some(id) = self
             .index
             .get_sid_from_cache(info)
@@ -265,7 +271,7 @@ impl Database {
 
         let id = self
             .index
-            .add_series_if_not_exists(info)
+            .add_series_if_not_exists(info, self.opt.storage.auto_index_tags)
             .context(error::IndexErrSnafu)?;
 
         Ok(id)
@@ -122,6 +122,9 @@ pub enum Error {
     #[snafu(display("tags or fields can't be empty"))]
     InvalidPoint,
 
+    #[snafu(display("point has {} tags, more than the limit {}", num, max))]
+    TooManyTags { num: usize, max: u32 },
+
     #[snafu(display("table not found for {}", table_name))]
     NotFoundTable { table_name: String },
 
@@ -18,12 +18,15 @@ use parking_lot::RwLock;
 use sled::Error;
 use snafu::ResultExt;
 
+use crate::kv_option::StorageOptions;
 use crate::Error::IndexErr;
 use config::Config;
 use datafusion::arrow::datatypes::{DataType, ToByteSlice};
 use datafusion::parquet::data_type::AsBytes;
 use models::codec::Encoding;
-use models::schema::{ColumnType, DatabaseSchema, TableColumn, TableSchema, TskvTableSchema};
+use models::schema::{
+    ColumnType, DatabaseSchema, TableColumn, TableSchema, TskvTableSchema, TskvTableSchemaBuilder,
+};
 use models::{
     tag::TagFromParts, utils, ColumnId, FieldId, FieldInfo, SeriesId, SeriesKey, Tag, ValueType,
 };
@@ -167,7 +170,9 @@ impl DBIndex {
         Ok(None)
     }
 
-    pub fn add_series_if_not_exists(&self, info: &Point) -> IndexResult<u64> {
+    /// Add the series of `info` if not exists, its tags are only added to the inverted index
+    /// if `index_tags` is set, otherwise `index_table_tags` adds them later
+    pub fn add_series_if_not_exists(&self, info: &Point, index_tags: bool) -> IndexResult<u64> {
         let mut series_key = SeriesKey::from_flatbuffer(info)
             .map_err(|e| IndexError::FieldType { msg: e.to_string() })?;
 
@@ -201,13 +206,51 @@ impl DBIndex {
             .set(stroage_key.as_bytes(), &bincode::serialize(&keys).unwrap())?;
         drop(series_cache);
 
-        for tag in series_key.tags() {
-            let key = encode_inverted_index_key(series_key.table(), &tag.key, &tag.value);
-            self.storage.push(&key, id.to_be_bytes().as_ref())?;
+        if index_tags {
+            for tag in series_key.tags() {
+                let key = encode_inverted_index_key(series_key.table(), &tag.key, &tag.value);
+                self.storage.push(&key, id.to_be_bytes().as_ref())?;
+            }
         }
         Ok(id)
     }
 
+    /// Add the tags of every series of table `tab` to the inverted index,
+    /// for the series added while tags were not indexed automatically
+    pub fn index_table_tags(&self, tab: &str) -> IndexResult<()> {
+        let mut index: HashMap<Vec<u8>, Vec<u64>> = HashMap::new();
+        for kv in self.storage.prefix(SERIES_KEY_PREFIX.as_bytes()) {
+            let (_, data) = kv?;
+            let keys = bincode::deserialize::<Vec<SeriesKey>>(&data).map_err(|e| {
+                IndexError::IndexStroage {
+                    msg: format!("failed to deserialize series keys, because {}", e),
+                }
+            })?;
+            for key in keys.iter().filter(|key| key.table() == tab) {
+                for tag in key.tags() {
+                    index
+                        .entry(encode_inverted_index_key(tab, &tag.key, &tag.value))
+                        .or_default()
+                        .push(key.id());
+                }
+            }
+        }
+
+        for (key, ids) in index {
+            let mut id_list = match self.storage.get(&key)? {
+                Some(data) => decode_series_id_list(&data)?,
+                None => vec![],
+            };
+            for id in ids {
+                if !id_list.contains(&id) {
+                    id_list.push(id);
+                }
+            }
+            self.storage.set(&key, &encode_series_id_list(&id_list))?;
+        }
+        Ok(())
+    }
+
     pub fn check_field_type_from_cache(&self, series_id: u64, info: &Point) -> IndexResult<()> {
         let table_name = unsafe { String::from_utf8_unchecked(info.tab().unwrap().to_vec()) };
         if let Some(schema) = self.table_schema.read().get(&table_name) {
@@ -257,7 +300,12 @@ impl DBIndex {
         }
     }
 
-    pub fn check_field_type_or_else_add(&self, series_id: u64, info: &Point) -> IndexResult<()> {
+    pub fn check_field_type_or_else_add(
+        &self,
+        series_id: u64,
+        info: &Point,
+        opt: &StorageOptions,
+    ) -> IndexResult<()> {
         //load schema first from cache,or else from storage and than cache it!
         let mut schema = &mut TskvTableSchema::default();
         let table_name = unsafe { String::from_utf8_unchecked(info.tab().unwrap().to_vec()) };
@@ -295,7 +343,7 @@ impl DBIndex {
         let mut schema_change = false;
         let mut check_fn = |field: &mut TableColumn| -> IndexResult<()> {
             let encoding = match schema.column(&field.name) {
-                None => Encoding::Default,
+                None => field.encoding,
                 Some(v) => v.encoding,
             };
             field.encoding = encoding;
@@ -318,6 +366,16 @@ impl DBIndex {
                     }
                 }
                 None => {
+                    if let Some(max_fields) = opt.max_fields_per_table {
+                        if field.column_type.is_field() {
+                            // Check the limit before the cached schema gets the new field
+                            let mut checked = schema.clone();
+                            checked.add_column(field.clone());
+                            checked
+                                .check_field_limit(max_fields)
+                                .context(errors::SchemaSnafu)?;
+                        }
+                    }
                     schema_change = true;
                     field.id = schema.next_column_id();
                     schema.add_column(field.clone());
@@ -326,10 +384,10 @@ impl DBIndex {
             Ok(())
         };
         //check timestamp
-        check_fn(&mut TableColumn::new_with_default(
-            TIME_STAMP_NAME.to_string(),
-            ColumnType::Time,
-        ))?;
+        let mut time_column =
+            TableColumn::new_with_default(TIME_STAMP_NAME.to_string(), ColumnType::Time);
+        time_column.encoding = opt.time_codec_default;
+        check_fn(&mut time_column)?;
 
         //check tags
         for tag in info.tags().unwrap() {
@@ -347,6 +405,13 @@ impl DBIndex {
         }
         //schema changed store it
         if new_schema {
+            // Assign the ids of a new table by the configured order
+            let builder = schema.columns().iter().fold(
+                TskvTableSchemaBuilder::new(schema.db.clone(), schema.name.clone())
+                    .with_deterministic_field_ids(opt.deterministic_field_ids),
+                |builder, column| builder.with_column(column.clone()),
+            );
+            *schema = builder.build();
             schema.schema_id = 0;
         } else if schema_change {
             schema.schema_id += 1;
@@ -760,8 +825,13 @@ pub fn tag_value_to_index_key(tab: &str, tag_key: &str, v: &ScalarValue) -> Vec<
 
 #[cfg(test)]
 mod test {
+    use config::get_config;
     use datafusion::arrow::datatypes::{DataType, Field, Schema};
-    use models::schema::ExternalTableSchema;
+    use models::schema::{DatabaseSchema, ExternalTableSchema, SchemaError, TableSchema};
+    use protos::{models as fb_models, models_helper};
+
+    use super::{DBIndex, IndexError};
+    use crate::kv_option::StorageOptions;
 
     #[test]
     fn test_serde() {
@@ -789,4 +859,96 @@ mod test {
 
         assert_eq!(ans, schema);
     }
+
+    #[test]
+    fn test_max_fields_per_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = DBIndex::new(dir.path(), DatabaseSchema::new("db0")).unwrap();
+        let mut fbb = flatbuffers::FlatBufferBuilder::new();
+        // Points of table 'table' with the fields 'fa' and 'fb'
+        let points = models_helper::create_const_points(&mut fbb, 1);
+        fbb.finish(points, None);
+        let points = flatbuffers::root::<fb_models::Points>(fbb.finished_data()).unwrap();
+        let point = points.points().unwrap().get(0);
+
+        let config = get_config("../config/config.toml").unwrap();
+        let mut opt = StorageOptions::from(&config);
+        opt.max_fields_per_table = Some(1);
+        let err = index
+            .check_field_type_or_else_add(0, &point, &opt)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            IndexError::Schema {
+                source: SchemaError::TooManyFields {
+                    field_num: 2,
+                    limit: 1,
+                    ..
+                }
+            }
+        ));
+
+        opt.max_fields_per_table = Some(2);
+        index.check_field_type_or_else_add(0, &point, &opt).unwrap();
+        match index.get_table_schema("table").unwrap() {
+            Some(TableSchema::TsKvTableSchema(schema)) => assert_eq!(schema.field_num(), 2),
+            _ => panic!("table schema not stored"),
+        }
+    }
+
+    #[test]
+    fn test_index_table_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = DBIndex::new(dir.path(), DatabaseSchema::new("db0")).unwrap();
+        let mut fbb = flatbuffers::FlatBufferBuilder::new();
+        let points = models_helper::create_const_points(&mut fbb, 1);
+        fbb.finish(points, None);
+        let points = flatbuffers::root::<fb_models::Points>(fbb.finished_data()).unwrap();
+        let point = points.points().unwrap().get(0);
+
+        let sid = index.add_series_if_not_exists(&point, false).unwrap();
+        assert!(index.get_series_id_list("table", &[]).unwrap().is_empty());
+
+        index.index_table_tags("table").unwrap();
+        assert_eq!(index.get_series_id_list("table", &[]).unwrap(), vec![sid]);
+
+        // Indexing again doesn't duplicate the series.
+        index.index_table_tags("table").unwrap();
+        assert_eq!(index.get_series_id_list("table", &[]).unwrap(), vec![sid]);
+    }
+
+    #[test]
+    fn test_deterministic_field_ids() {
+        let mut fbb = flatbuffers::FlatBufferBuilder::new();
+        // Points of table 'table' with the tags 'ta', 'tb' and the fields 'fa', 'fb'
+        let points = models_helper::create_const_points(&mut fbb, 1);
+        fbb.finish(points, None);
+        let points = flatbuffers::root::<fb_models::Points>(fbb.finished_data()).unwrap();
+        let point = points.points().unwrap().get(0);
+
+        let config = get_config("../config/config.toml").unwrap();
+        let mut opt = StorageOptions::from(&config);
+        for (deterministic, expected) in [
+            (
+                false,
+                [("time", 0), ("ta", 1), ("tb", 2), ("fa", 3), ("fb", 4)],
+            ),
+            (
+                true,
+                [("fa", 0), ("fb", 1), ("ta", 2), ("tb", 3), ("time", 4)],
+            ),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let index = DBIndex::new(dir.path(), DatabaseSchema::new("db0")).unwrap();
+            opt.deterministic_field_ids = deterministic;
+            index.check_field_type_or_else_add(0, &point, &opt).unwrap();
+            let schema = match index.get_table_schema("table").unwrap() {
+                Some(TableSchema::TsKvTableSchema(schema)) => schema,
+                _ => panic!("table schema not stored"),
+            };
+            for (name, id) in expected {
+                assert_eq!(schema.column(name).unwrap().id, id);
+            }
+        }
+    }
 }
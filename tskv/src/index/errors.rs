@@ -1,3 +1,4 @@
+use models::schema::SchemaError;
 use snafu::Snafu;
 
 use crate::record_file;
@@ -34,6 +35,9 @@ pub enum IndexError {
 
     #[snafu(display("column '{}' already exists", column))]
     ColumnAlreadyExists { column: String },
+
+    #[snafu(display("{}", source))]
+    Schema { source: SchemaError },
 }
 
 impl From<sled::Error> for IndexError {
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use config::Config;
+use models::codec::Encoding;
 use serde::{Deserialize, Serialize};
 
 use crate::{file_system, index::IndexConfig, summary};
@@ -41,6 +42,12 @@ pub struct StorageOptions {
     pub compact_trigger: u32,
     pub max_compact_size: u64,
     pub strict_write: bool,
+    pub time_codec_default: Encoding,
+    pub verify_checksums_on_read: bool,
+    pub max_fields_per_table: Option<usize>,
+    pub max_tags_per_series: Option<u32>,
+    pub auto_index_tags: bool,
+    pub deterministic_field_ids: bool,
 }
 
 impl StorageOptions {
@@ -87,6 +94,12 @@ impl From<&Config> for StorageOptions {
             compact_trigger: config.storage.compact_trigger,
             max_compact_size: config.storage.max_compact_size,
             strict_write: config.storage.strict_write,
+            time_codec_default: config.storage.time_codec_default(),
+            verify_checksums_on_read: config.storage.verify_checksums_on_read(),
+            max_fields_per_table: config.storage.max_fields_per_table,
+            max_tags_per_series: config.storage.max_tags_per_series(),
+            auto_index_tags: config.storage.auto_index_tags(),
+            deterministic_field_ids: config.storage.deterministic_field_ids(),
         }
     }
 }
@@ -94,12 +107,20 @@ impl From<&Config> for StorageOptions {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct QueryOptions {
     pub max_server_connections: u32,
+    pub max_expr_depth: u32,
+    pub query_tracing: bool,
+    pub query_timeout: Option<Duration>,
+    pub partial_results_on_timeout: bool,
 }
 
 impl From<&Config> for QueryOptions {
     fn from(config: &Config) -> Self {
         Self {
             max_server_connections: config.query.max_server_connections,
+            max_expr_depth: config.query.max_expr_depth,
+            query_tracing: config.log.query_tracing(),
+            query_timeout: config.query.query_timeout(),
+            partial_results_on_timeout: config.query.partial_results_on_timeout(),
         }
     }
 }
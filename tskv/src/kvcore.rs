@@ -755,7 +755,7 @@ mod test {
     #[ignore]
     async fn test_compact() {
         trace::init_default_global_tracing("tskv_log", "tskv.log", "info");
-        let config = get_config("/tmp/test/config/config.toml");
+        let config = get_config("/tmp/test/config/config.toml").unwrap();
         let opt = Options::from(&config);
         let tskv = TsKv::open(opt, Arc::new(Runtime::new().unwrap()))
             .await
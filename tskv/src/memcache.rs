@@ -116,7 +116,7 @@ impl RowData {
     pub fn point_to_row_data(p: fb_models::Point, schema: &TskvTableSchema) -> RowData {
         let fields = match p.fields() {
             None => {
-                let mut fields = Vec::with_capacity(schema.field_num());
+                let mut fields = Vec::with_capacity(schema.fields_id().len());
                 for i in 0..fields.capacity() {
                     fields.push(None);
                 }
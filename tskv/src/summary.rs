@@ -599,7 +599,7 @@ mod test {
     #[tokio::test]
     async fn test_summary() {
         let base_dir = "/tmp/test/summary/1".to_string();
-        let mut config = get_config("../config/config.toml");
+        let mut config = get_config("../config/config.toml").unwrap();
         config.storage.path = base_dir.clone();
         let opt = Arc::new(Options::from(&config));
 
@@ -375,7 +375,7 @@ impl LevelInfo {
             }
 
             let tsm_reader = match TsmReader::open(file.file_path()).await {
-                Ok(tr) => tr,
+                Ok(tr) => tr.with_verify_checksums(self.storage_opt.verify_checksums_on_read),
                 Err(e) => {
                     error!("failed to load tsm reader, in case {:?}", e);
                     return vec![];
@@ -844,7 +844,7 @@ mod test {
         let dir = "/tmp/test/ts_family/1";
         let _ = std::fs::remove_dir(dir);
         std::fs::create_dir_all(dir).unwrap();
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.storage.path = dir.to_string();
         let opt = Arc::new(Options::from(&global_config));
 
@@ -943,7 +943,7 @@ mod test {
         let dir = "/tmp/test/ts_family/2";
         let _ = std::fs::remove_dir(dir);
         std::fs::create_dir_all(dir).unwrap();
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.storage.path = dir.to_string();
         let opt = Arc::new(Options::from(&global_config));
 
@@ -1057,7 +1057,7 @@ mod test {
         let dir = "/tmp/test/ts_family/tsf_delete";
         let _ = std::fs::remove_dir(dir);
         std::fs::create_dir_all(dir).unwrap();
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.storage.path = dir.to_string();
         let opt = Arc::new(Options::from(&global_config));
 
@@ -1175,7 +1175,7 @@ mod test {
         let dir = "/tmp/test/ts_family/read_with_tomb";
         let _ = std::fs::remove_dir(dir);
         std::fs::create_dir_all(dir).unwrap();
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.storage.path = dir.to_string();
         let opt = Arc::new(Options::from(&global_config));
 
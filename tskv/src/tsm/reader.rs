@@ -42,6 +42,9 @@ pub enum ReadTsmError {
 
     #[snafu(display("TSM file is invalid: {}", reason))]
     Invalid { reason: String },
+
+    #[snafu(display("TSM data block at offset {} failed checksum verification", offset))]
+    CrcCheck { offset: u64 },
 }
 
 impl From<ReadTsmError> for Error {
@@ -439,6 +442,7 @@ pub struct TsmReader {
     reader: Arc<AsyncFile>,
     index_reader: Arc<IndexReader>,
     tombstone: Arc<RwLock<TsmTombstone>>,
+    verify_checksums: bool,
 }
 
 impl TsmReader {
@@ -453,9 +457,16 @@ impl TsmReader {
             reader: tsm,
             index_reader: Arc::new(tsm_idx),
             tombstone: Arc::new(RwLock::new(tombstone)),
+            verify_checksums: false,
         })
     }
 
+    /// Verify the checksums of every data block read by this reader
+    pub fn with_verify_checksums(mut self, verify_checksums: bool) -> Self {
+        self.verify_checksums = verify_checksums;
+        self
+    }
+
     pub fn index_iterator(&self) -> IndexIterator {
         self.index_reader.iter()
     }
@@ -474,6 +485,7 @@ impl TsmReader {
             block_meta.field_type(),
             block_meta.offset(),
             block_meta.val_off(),
+            self.verify_checksums,
         )
         .await?;
         self.tombstone
@@ -483,6 +495,7 @@ impl TsmReader {
     }
 
     // Reads raw data from file and returns the read data size.
+    // The checksums are verified if enabled, though the data is not decoded.
     pub async fn get_raw_data(
         &self,
         block_meta: &BlockMeta,
@@ -496,6 +509,13 @@ impl TsmReader {
             .read_at(block_meta.offset(), &mut dst[..data_len])
             .await
             .context(IOSnafu)?;
+        if self.verify_checksums
+            && !verify_data_block(&dst[..data_len], block_meta.val_off() - block_meta.offset())
+        {
+            return Err(ReadTsmError::CrcCheck {
+                offset: block_meta.offset(),
+            });
+        }
         Ok(data_len)
     }
 
@@ -528,6 +548,7 @@ pub struct ColumnReader {
     reader: Arc<AsyncFile>,
     inner: BlockMetaIterator,
     buf: Vec<u8>,
+    verify_checksums: bool,
 }
 
 impl ColumnReader {
@@ -536,9 +557,16 @@ impl ColumnReader {
             reader,
             inner,
             buf: vec![],
+            verify_checksums: false,
         }
     }
 
+    /// Verify the checksums of every data block read by this reader
+    pub fn with_verify_checksums(mut self, verify_checksums: bool) -> Self {
+        self.verify_checksums = verify_checksums;
+        self
+    }
+
     async fn decode(&mut self, block_meta: &BlockMeta) -> ReadTsmResult<DataBlock> {
         let (offset, size) = (block_meta.offset(), block_meta.size());
         self.buf.resize(size as usize, 0);
@@ -548,6 +576,7 @@ impl ColumnReader {
             block_meta.field_type(),
             block_meta.offset(),
             block_meta.val_off(),
+            self.verify_checksums,
         )
         .await
     }
@@ -569,11 +598,26 @@ async fn read_data_block(
     field_type: ValueType,
     offset: u64,
     val_off: u64,
+    verify_checksums: bool,
 ) -> ReadTsmResult<DataBlock> {
     reader.read_at(offset, buf).await.context(IOSnafu)?;
+    if verify_checksums && !verify_data_block(buf, val_off - offset) {
+        return Err(ReadTsmError::CrcCheck { offset });
+    }
     decode_data_block(buf, field_type, val_off - offset)
 }
 
+/// Whether the crc32 checksums of the timestamps and the values of a data block are correct
+pub fn verify_data_block(buf: &[u8], val_off: u64) -> bool {
+    let val_off = val_off as usize;
+    if val_off < 4 || buf.len() < val_off + 4 {
+        return false;
+    }
+    let ts_crc = decode_be_u32(&buf[..4]);
+    let val_crc = decode_be_u32(&buf[val_off..val_off + 4]);
+    ts_crc == crc32fast::hash(&buf[4..val_off]) && val_crc == crc32fast::hash(&buf[val_off + 4..])
+}
+
 pub fn decode_data_block(
     buf: &[u8],
     field_type: ValueType,
@@ -678,7 +722,7 @@ pub mod tsm_reader_tests {
     use models::{FieldId, Timestamp};
     use parking_lot::Mutex;
 
-    use super::print_tsm_statistics;
+    use super::{print_tsm_statistics, ReadTsmError};
     use crate::file_system::file_manager::{self, get_file_manager};
     use crate::tsm::codec::DataBlockEncoding;
     use crate::{
@@ -794,6 +838,39 @@ pub mod tsm_reader_tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_tsm_reader_verify_checksums() {
+        let (tsm_file, _) = prepare("/tmp/test/tsm_reader/verify_checksums").await;
+        let reader = TsmReader::open(&tsm_file)
+            .await
+            .unwrap()
+            .with_verify_checksums(true);
+        let blk = reader
+            .index_iterator_opt(1)
+            .next()
+            .unwrap()
+            .block_iterator()
+            .next()
+            .unwrap();
+        reader.get_data_block(&blk).await.unwrap();
+        drop(reader);
+
+        // Flip the last byte of the values of the block.
+        let mut data = std::fs::read(&tsm_file).unwrap();
+        let pos = (blk.offset() + blk.size() - 1) as usize;
+        data[pos] = !data[pos];
+        std::fs::write(&tsm_file, data).unwrap();
+
+        let reader = TsmReader::open(&tsm_file)
+            .await
+            .unwrap()
+            .with_verify_checksums(true);
+        assert!(matches!(
+            reader.get_data_block(&blk).await,
+            Err(ReadTsmError::CrcCheck { .. })
+        ));
+    }
+
     #[tokio::test]
     async fn test_tsm_reader_2() {
         let (tsm_file, tombstone_file) = prepare("/tmp/test/tsm_reader/2").await;
@@ -521,7 +521,8 @@ mod test {
         let index = IndexReader::open(file.clone()).await.unwrap();
         let mut data: HashMap<FieldId, Vec<DataBlock>> = HashMap::new();
         for idx_meta in index.iter() {
-            let mut cr = ColumnReader::new(file.clone(), idx_meta.block_iterator());
+            let mut cr = ColumnReader::new(file.clone(), idx_meta.block_iterator())
+                .with_verify_checksums(true);
             loop {
                 match cr.next().await {
                     None => break,
@@ -526,7 +526,7 @@ mod test {
     async fn test_read_and_write() {
         let dir = "/tmp/test/wal/1".to_string();
         let _ = std::fs::remove_dir_all(dir.clone()); // Ignore errors
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.wal.path = dir.clone();
         let wal_config = WalOptions::from(&global_config);
 
@@ -562,7 +562,7 @@ mod test {
 
         let dir = "/tmp/test/wal/2".to_string();
         let _ = std::fs::remove_dir_all(dir.clone()); // Ignore errors
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.wal.path = dir.clone();
         global_config.wal.sync = false;
         let wal_config = WalOptions::from(&global_config);
@@ -599,7 +599,7 @@ mod test {
         init_default_global_tracing("tskv_log", "tskv.log", "debug");
         let dir = "/tmp/test/wal/3".to_string();
         let _ = std::fs::remove_dir_all(dir.clone()); // Ignore errors
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.wal.path = dir.clone();
         let wal_config = WalOptions::from(&global_config);
 
@@ -632,7 +632,7 @@ mod test {
         let rt = Arc::new(runtime::Runtime::new().unwrap());
         let dir = "/tmp/test/wal/4/wal";
         let _ = std::fs::remove_dir_all(dir);
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.wal.path = dir.to_string();
         global_config.storage.path = "/tmp/test/wal/4".to_string();
         let wal_config = WalOptions::from(&global_config);
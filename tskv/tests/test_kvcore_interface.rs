@@ -22,7 +22,7 @@ mod tests {
 
     fn get_tskv(dir: impl AsRef<Path>) -> (Arc<Runtime>, TsKv) {
         let dir = dir.as_ref();
-        let mut global_config = get_config("../config/config.toml");
+        let mut global_config = get_config("../config/config.toml").unwrap();
         global_config.wal.path = dir.join("wal").to_str().unwrap().to_string();
         global_config.storage.path = dir.to_str().unwrap().to_string();
         global_config.cache.max_buffer_size = 128;